@@ -0,0 +1,120 @@
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::ChannelMessage;
+
+/// Server-side block relationships (blocker -> blocked users) and the
+/// suppression rules built on them
+#[derive(Debug, Clone, Default)]
+pub struct BlockList {
+    blocks: HashMap<Uuid, HashSet<Uuid>>,
+}
+
+impl BlockList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns false if the block already existed or the user tried to block themselves
+    pub fn block(&mut self, blocker: Uuid, target: Uuid) -> bool {
+        blocker != target && self.blocks.entry(blocker).or_default().insert(target)
+    }
+
+    pub fn unblock(&mut self, blocker: Uuid, target: Uuid) -> bool {
+        self.blocks.get_mut(&blocker).is_some_and(|set| set.remove(&target))
+    }
+
+    pub fn is_blocked(&self, blocker: Uuid, target: Uuid) -> bool {
+        self.blocks.get(&blocker).is_some_and(|set| set.contains(&target))
+    }
+
+    pub fn blocked_by(&self, blocker: Uuid) -> impl Iterator<Item = Uuid> + '_ {
+        self.blocks.get(&blocker).into_iter().flatten().copied()
+    }
+
+    /// DMs are suppressed when either side has blocked the other
+    pub fn can_direct_message(&self, from: Uuid, to: Uuid) -> bool {
+        !self.is_blocked(to, from) && !self.is_blocked(from, to)
+    }
+
+    /// Covers `SendServerInvite` and `AcceptServerInviteFromUser`. Symmetric like
+    /// DMs, so a blocker can't reach someone who can no longer answer them.
+    pub fn can_send_invite(&self, from: Uuid, to: Uuid) -> bool {
+        self.can_direct_message(from, to)
+    }
+
+    /// Mentions still appear in the channel, but the mentioned user isn't notified
+    pub fn should_notify_mention(&self, from: Uuid, mentioned: Uuid) -> bool {
+        !self.is_blocked(mentioned, from)
+    }
+}
+
+/// Client-side filter hiding channel messages from ignored users
+pub fn visible_messages<'a>(
+    messages: &'a [ChannelMessage],
+    ignored: &'a HashSet<Uuid>,
+) -> impl Iterator<Item = &'a ChannelMessage> + 'a {
+    messages.iter().filter(move |m| !ignored.contains(&m.sent_by))
+}
+
+/// Number of messages `visible_messages` hides, for rendering "N messages from ignored users" placeholders
+pub fn count_hidden(messages: &[ChannelMessage], ignored: &HashSet<Uuid>) -> usize {
+    messages.iter().filter(|m| ignored.contains(&m.sent_by)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(sent_by: Uuid) -> ChannelMessage {
+        ChannelMessage { id: Uuid::new_v4(), channel_id: Uuid::nil(), sent_by, timestamp: 0, content: "hi".into() }
+    }
+
+    #[test]
+    fn block_and_unblock() {
+        let mut blocks = BlockList::new();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        assert!(!blocks.block(a, a));
+        assert!(blocks.block(a, b));
+        assert!(!blocks.block(a, b));
+        assert!(blocks.is_blocked(a, b));
+        assert!(!blocks.is_blocked(b, a));
+        assert_eq!(blocks.blocked_by(a).collect::<Vec<_>>(), vec![b]);
+        assert!(blocks.unblock(a, b));
+        assert!(!blocks.unblock(a, b));
+        assert!(!blocks.is_blocked(a, b));
+    }
+
+    #[test]
+    fn dms_and_invites_are_blocked_both_ways() {
+        let mut blocks = BlockList::new();
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        blocks.block(a, b);
+        for (from, to) in [(a, b), (b, a)] {
+            assert!(!blocks.can_direct_message(from, to));
+            assert!(!blocks.can_send_invite(from, to));
+        }
+        assert!(blocks.can_direct_message(a, c));
+        assert!(blocks.can_send_invite(c, a));
+    }
+
+    #[test]
+    fn mentions_only_skip_the_blocker() {
+        let mut blocks = BlockList::new();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        blocks.block(a, b);
+        assert!(!blocks.should_notify_mention(b, a));
+        assert!(blocks.should_notify_mention(a, b));
+    }
+
+    #[test]
+    fn ignored_messages_are_hidden_and_counted() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let messages = vec![message(a), message(b), message(a)];
+        let ignored = HashSet::from([a]);
+        let visible: Vec<_> = visible_messages(&messages, &ignored).map(|m| m.sent_by).collect();
+        assert_eq!(visible, vec![b]);
+        assert_eq!(count_hidden(&messages, &ignored), 2);
+        assert_eq!(count_hidden(&messages, &HashSet::new()), 0);
+    }
+}
@@ -68,6 +68,31 @@ pub struct SecurityConfig {
     pub audit_logging_enabled: bool,
     pub ip_whitelist: Vec<String>,
     pub ip_blacklist: Vec<String>,
+    // Argon2id password hashing cost, see the `crypto` module
+    #[serde(default = "default_hash_memory_kib")]
+    pub hash_memory_kib: u32,
+    #[serde(default = "default_hash_iterations")]
+    pub hash_iterations: u32,
+    #[serde(default = "default_hash_parallelism")]
+    pub hash_parallelism: u32,
+    #[serde(default = "default_account_deletion_grace_days")]
+    pub account_deletion_grace_days: u32,
+}
+
+fn default_hash_memory_kib() -> u32 {
+    19 * 1024
+}
+
+fn default_hash_iterations() -> u32 {
+    2
+}
+
+fn default_hash_parallelism() -> u32 {
+    1
+}
+
+fn default_account_deletion_grace_days() -> u32 {
+    30
 }
 
 impl Default for ServerConfig {
@@ -125,6 +150,10 @@ impl Default for ServerConfig {
                 audit_logging_enabled: true,
                 ip_whitelist: vec![],
                 ip_blacklist: vec![],
+                hash_memory_kib: default_hash_memory_kib(),
+                hash_iterations: default_hash_iterations(),
+                hash_parallelism: default_hash_parallelism(),
+                account_deletion_grace_days: default_account_deletion_grace_days(),
             },
         }
     }
@@ -161,6 +190,11 @@ impl ServerConfig {
         Ok(())
     }
     
+    /// When an account deleted at `requested_at` gets purged
+    pub fn account_deletion_time(&self, requested_at: i64) -> i64 {
+        requested_at + self.security.account_deletion_grace_days as i64 * 24 * 60 * 60
+    }
+    
     /// Validate configuration values
     pub fn validate(&self) -> Result<(), String> {
         if self.network.port == 0 {
@@ -179,10 +213,26 @@ impl ServerConfig {
             return Err("Message length limit must be greater than 0".to_string());
         }
         
+        for pattern in &self.moderation.blocked_patterns {
+            if let Err(e) = regex::Regex::new(pattern) {
+                return Err(format!("Invalid blocked pattern '{}': {}", pattern, e));
+            }
+        }
+        
         if self.security.min_password_length < 4 {
             return Err("Minimum password length must be at least 4".to_string());
         }
         
+        crate::ip_filter::IpFilter::from_config(&self.security)?;
+        
+        if self.security.hash_iterations == 0 || self.security.hash_parallelism == 0 {
+            return Err("Hash iterations and parallelism must be greater than 0".to_string());
+        }
+        
+        if self.security.hash_memory_kib < 8 * self.security.hash_parallelism {
+            return Err("Hash memory must be at least 8 KiB per lane of parallelism".to_string());
+        }
+        
         Ok(())
     }
 }
@@ -195,6 +245,8 @@ pub struct ClientConfig {
     pub notifications: NotificationConfig,
     pub audio: AudioConfig,
     pub performance: PerformanceConfig,
+    #[serde(default)]
+    pub presence: PresenceConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -217,6 +269,8 @@ pub struct UiConfig {
     pub compact_mode: bool,
     pub sidebar_width: u16,
     pub max_message_history: usize,
+    #[serde(default)]
+    pub ignored_users: Vec<uuid::Uuid>, // Hidden client-side only, see `blocking::visible_messages`
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -249,6 +303,21 @@ pub struct PerformanceConfig {
     pub scroll_buffer_size: usize,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceConfig {
+    pub auto_away_enabled: bool,
+    pub auto_away_minutes: u64,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            auto_away_enabled: true,
+            auto_away_minutes: 10,
+        }
+    }
+}
+
 impl Default for ClientConfig {
     fn default() -> Self {
         Self {
@@ -269,6 +338,7 @@ impl Default for ClientConfig {
                 compact_mode: false,
                 sidebar_width: 30,
                 max_message_history: 1000,
+                ignored_users: vec![],
             },
             notifications: NotificationConfig {
                 enabled: true,
@@ -294,6 +364,7 @@ impl Default for ClientConfig {
                 message_render_limit: 100,
                 scroll_buffer_size: 500,
             },
+            presence: PresenceConfig::default(),
         }
     }
 }
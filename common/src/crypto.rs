@@ -0,0 +1,112 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+
+use crate::config::SecurityConfig;
+
+pub use argon2::password_hash::Error as HashError;
+
+/// Build the Argon2id hasher described by the security config
+fn argon2(config: &SecurityConfig) -> Result<Argon2<'static>, HashError> {
+    let params = Params::new(config.hash_memory_kib, config.hash_iterations, config.hash_parallelism, None)
+        .map_err(HashError::from)?;
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+}
+
+/// Hash a password with Argon2id and a random salt.
+/// The result is a PHC string (`$argon2id$v=19$m=...,t=...,p=...$salt$hash`) suitable for `UserAccount.hash`.
+pub fn hash_password(password: &str, config: &SecurityConfig) -> Result<String, HashError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(argon2(config)?.hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+/// Check a password against a stored PHC string.
+/// Uses the parameters embedded in the hash, so old hashes keep verifying after the config changes.
+pub fn verify_password(password: &str, phc: &str) -> Result<bool, HashError> {
+    let hash = PasswordHash::new(phc)?;
+    match Argon2::default().verify_password(password.as_bytes(), &hash) {
+        Ok(()) => Ok(true),
+        Err(HashError::Password) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether a stored hash should be replaced on the user's next successful login:
+/// it isn't Argon2id v19, uses different cost parameters than the config, or can't be parsed.
+pub fn needs_rehash(phc: &str, config: &SecurityConfig) -> bool {
+    let Ok(hash) = PasswordHash::new(phc) else {
+        return true;
+    };
+    if hash.algorithm != argon2::ARGON2ID_IDENT || hash.version != Some(Version::V0x13.into()) {
+        return true;
+    }
+    match Params::try_from(&hash) {
+        Ok(params) => {
+            params.m_cost() != config.hash_memory_kib
+                || params.t_cost() != config.hash_iterations
+                || params.p_cost() != config.hash_parallelism
+        }
+        Err(_) => true,
+    }
+}
+
+#[cfg(all(test, feature = "crypto"))]
+mod tests {
+    use super::*;
+    use crate::ServerConfig;
+
+    // Low cost parameters keep the tests fast
+    fn config() -> SecurityConfig {
+        SecurityConfig { hash_memory_kib: 64, hash_iterations: 1, hash_parallelism: 1, ..ServerConfig::default().security }
+    }
+
+    #[test]
+    fn hash_round_trips() {
+        let config = config();
+        let hash = hash_password("correct horse", &config).unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$m=64,t=1,p=1$"), "{}", hash);
+        assert_eq!(verify_password("correct horse", &hash), Ok(true));
+        assert!(!needs_rehash(&hash, &config));
+    }
+
+    #[test]
+    fn salts_are_random() {
+        let config = config();
+        assert_ne!(hash_password("same", &config).unwrap(), hash_password("same", &config).unwrap());
+    }
+
+    #[test]
+    fn wrong_password_is_rejected() {
+        let hash = hash_password("correct horse", &config()).unwrap();
+        assert_eq!(verify_password("battery staple", &hash), Ok(false));
+    }
+
+    #[test]
+    fn malformed_hash_is_an_error() {
+        assert!(verify_password("anything", "not a phc string").is_err());
+        assert!(verify_password("anything", "$argon2id$v=19$m=64,t=1,p=1$c2FsdHNhbHQ$not*base64").is_err());
+        assert!(verify_password("anything", "$sha1$c2FsdHNhbHQ$c2VjcmV0aGFzaA").is_err());
+        assert!(needs_rehash("not a phc string", &config()));
+    }
+
+    #[test]
+    fn changed_params_need_rehash() {
+        let config = config();
+        let hash = hash_password("pw", &config).unwrap();
+
+        let stronger = SecurityConfig { hash_iterations: 2, ..config.clone() };
+        assert!(needs_rehash(&hash, &stronger));
+        let more_memory = SecurityConfig { hash_memory_kib: 128, ..config.clone() };
+        assert!(needs_rehash(&hash, &more_memory));
+        // Old hashes still verify under the new config
+        assert_eq!(verify_password("pw", &hash), Ok(true));
+    }
+
+    #[test]
+    fn other_algorithms_need_rehash() {
+        let argon2i = Argon2::new(Algorithm::Argon2i, Version::V0x13, Params::new(64, 1, 1, None).unwrap());
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = argon2i.hash_password(b"pw", &salt).unwrap().to_string();
+        assert!(needs_rehash(&hash, &config()));
+    }
+}
@@ -0,0 +1,500 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    Forum, ForumCategory, ForumLightweight, ForumSummary, PaginationCursor, Post, PostLightweight, PostRevision, Thread,
+    ThreadLightweight, ThreadSort, ThreadSummary, UserRole,
+};
+
+/// Threads per page for `GetForumThreads`
+pub const THREADS_PAGE_SIZE: usize = 25;
+
+/// Posts per page for `GetThreadPosts`
+pub const POSTS_PAGE_SIZE: usize = 50;
+
+/// Why a forum action was refused
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ForumError {
+    ThreadNotFound,
+    ForumNotFound,
+    ThreadLocked,
+    NotPermitted,
+    PostNotFound,
+    OwnPost, // Voting on your own post
+    OpeningPost, // The opening post can't be the accepted answer
+    UnsupportedCursor, // Timestamp cursor on a listing that isn't chronological
+}
+
+impl std::fmt::Display for ForumError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ForumError::ThreadNotFound => write!(f, "Thread not found"),
+            ForumError::ForumNotFound => write!(f, "Forum not found"),
+            ForumError::ThreadLocked => write!(f, "This thread is locked"),
+            ForumError::NotPermitted => write!(f, "You don't have permission to do that"),
+            ForumError::PostNotFound => write!(f, "Post not found"),
+            ForumError::OwnPost => write!(f, "You can't vote on your own post"),
+            ForumError::OpeningPost => write!(f, "The opening post can't be marked as the answer"),
+            ForumError::UnsupportedCursor => write!(f, "This listing can only be paged by offset"),
+        }
+    }
+}
+
+/// Authors can edit their own posts and thread titles; moderators and admins can edit anyone's
+pub fn can_edit(editor: Uuid, editor_role: UserRole, author: Uuid) -> bool {
+    editor == author || editor_role >= UserRole::Moderator
+}
+
+/// Revision history is visible to the post's author and to moderators,
+/// so edits can be checked during moderation disputes
+pub fn can_view_revisions(viewer: Uuid, viewer_role: UserRole, author: Uuid) -> bool {
+    viewer == author || viewer_role >= UserRole::Moderator
+}
+
+impl Post {
+    /// Replace the content, returning the revision that records the old version.
+    /// Returns None when the content is unchanged so no empty revision is stored.
+    pub fn edit(&mut self, content: String, edited_by: Uuid, now: i64) -> Option<PostRevision> {
+        if content == self.content {
+            return None;
+        }
+        let previous = std::mem::replace(&mut self.content, content);
+        self.edited_at = Some(now);
+        Some(PostRevision {
+            id: Uuid::new_v4(),
+            post_id: self.id,
+            content: previous,
+            edited_by,
+            edited_at: now,
+        })
+    }
+}
+
+/// Pinning, locking and moving threads is restricted to moderators and admins
+pub fn can_moderate_threads(role: UserRole) -> bool {
+    role >= UserRole::Moderator
+}
+
+impl Thread {
+    /// Gate for `CreatePost` and `CreatePostReply`
+    pub fn check_can_post(&self) -> Result<(), ForumError> {
+        if self.locked {
+            Err(ForumError::ThreadLocked)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The thread author and moderators can choose the accepted answer, which must be a
+    /// reply in this thread rather than the opening post
+    pub fn check_mark_accepted(&self, actor: Uuid, actor_role: UserRole, post_id: Uuid) -> Result<(), ForumError> {
+        if actor != self.author.id && !can_moderate_threads(actor_role) {
+            return Err(ForumError::NotPermitted);
+        }
+        let index = self.posts.iter().position(|p| p.id == post_id).ok_or(ForumError::PostNotFound)?;
+        if index == 0 {
+            return Err(ForumError::OpeningPost);
+        }
+        Ok(())
+    }
+
+    /// Accepted answer first, then the remaining replies by score, keeping the
+    /// opening post on top. Ties keep chronological order.
+    pub fn answers_first(&self) -> Vec<&Post> {
+        let mut posts: Vec<&Post> = self.posts.iter().collect();
+        if let Some((_, replies)) = posts.split_first_mut() {
+            replies.sort_by_key(|p| (Some(p.id) != self.accepted_answer, std::cmp::Reverse(p.score)));
+        }
+        posts
+    }
+
+    pub fn last_post(&self) -> Option<&Post> {
+        self.posts.iter().max_by_key(|p| p.timestamp)
+    }
+
+    /// Time of the latest post, or of the thread itself if it has none
+    pub fn last_activity(&self) -> i64 {
+        self.last_post().map_or(self.timestamp, |p| p.timestamp)
+    }
+
+    pub fn summary(&self, forum_id: Uuid) -> ThreadSummary {
+        let last = self.last_post();
+        ThreadSummary {
+            id: self.id,
+            forum_id,
+            title: self.title.clone(),
+            author: (&self.author).into(),
+            timestamp: self.timestamp,
+            post_count: self.posts.len(),
+            last_post_at: self.last_activity(),
+            last_author: last.map(|p| (&p.author).into()),
+            edited_at: self.edited_at,
+            pinned: self.pinned,
+            locked: self.locked,
+            tags: self.tags.clone(),
+            score: self.posts.first().map_or(0, |p| p.score),
+            solved: self.accepted_answer.is_some(),
+        }
+    }
+}
+
+/// Pinned threads first, then most recent first
+pub fn sort_threads(threads: &mut [ThreadLightweight]) {
+    threads.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.timestamp.cmp(&a.timestamp)));
+}
+
+/// Move a thread between forums, returning the id of the forum it came from.
+/// Tags the destination forum doesn't define are dropped from the thread.
+pub fn move_thread(forums: &mut [Forum], thread_id: Uuid, to_forum_id: Uuid) -> Result<Uuid, ForumError> {
+    let to = forums.iter().position(|f| f.id == to_forum_id).ok_or(ForumError::ForumNotFound)?;
+    let from = forums
+        .iter()
+        .position(|f| f.threads.iter().any(|t| t.id == thread_id))
+        .ok_or(ForumError::ThreadNotFound)?;
+    if from != to {
+        let index = forums[from].threads.iter().position(|t| t.id == thread_id).ok_or(ForumError::ThreadNotFound)?;
+        let mut thread = forums[from].threads.remove(index);
+        forums[to].retain_known_tags(&mut thread.tags);
+        forums[to].threads.push(thread);
+    }
+    Ok(forums[from].id)
+}
+
+impl From<&Post> for PostLightweight {
+    fn from(post: &Post) -> Self {
+        Self {
+            id: post.id,
+            author: (&post.author).into(),
+            content: post.content.clone(),
+            timestamp: post.timestamp,
+            reply_to: post.reply_to,
+            edited_at: post.edited_at,
+            score: post.score,
+            my_vote: None,
+        }
+    }
+}
+
+impl From<&Thread> for ThreadLightweight {
+    fn from(thread: &Thread) -> Self {
+        Self {
+            id: thread.id,
+            title: thread.title.clone(),
+            author: (&thread.author).into(),
+            posts: thread.posts.iter().map(Into::into).collect(),
+            timestamp: thread.timestamp,
+            edited_at: thread.edited_at,
+            pinned: thread.pinned,
+            locked: thread.locked,
+            tags: thread.tags.clone(),
+            accepted_answer: thread.accepted_answer,
+        }
+    }
+}
+
+impl From<&Forum> for ForumLightweight {
+    /// Threads come out in display order, pinned first
+    fn from(forum: &Forum) -> Self {
+        let mut threads: Vec<ThreadLightweight> = forum.threads.iter().map(Into::into).collect();
+        sort_threads(&mut threads);
+        Self {
+            id: forum.id,
+            name: forum.name.clone(),
+            description: forum.description.clone(),
+            threads,
+            category_id: forum.category_id,
+            position: forum.position,
+            tags: forum.tags.clone(),
+        }
+    }
+}
+
+impl Forum {
+    pub fn summary(&self) -> ForumSummary {
+        ForumSummary {
+            id: self.id,
+            name: self.name.clone(),
+            description: self.description.clone(),
+            thread_count: self.threads.len(),
+            last_post_at: self.threads.iter().map(Thread::last_activity).max(),
+            category_id: self.category_id,
+            position: self.position,
+            tags: self.tags.clone(),
+        }
+    }
+
+    /// Thread summaries in listing order: pinned first, then by `sort`,
+    /// optionally restricted to threads carrying `tag`
+    pub fn thread_summaries(&self, sort: ThreadSort, tag: Option<Uuid>) -> Vec<ThreadSummary> {
+        let mut summaries: Vec<ThreadSummary> = self
+            .threads
+            .iter()
+            .filter(|t| tag.is_none_or(|tag| t.tags.contains(&tag)))
+            .map(|t| t.summary(self.id))
+            .collect();
+        sort_summaries(&mut summaries, sort);
+        summaries
+    }
+
+    /// Drop tag ids that aren't defined on this forum, and any duplicates
+    pub fn retain_known_tags(&self, tag_ids: &mut Vec<Uuid>) {
+        let mut seen = std::collections::HashSet::new();
+        tag_ids.retain(|id| self.tags.iter().any(|t| t.id == *id) && seen.insert(*id));
+    }
+
+    /// Delete a tag and take it off every thread, for `DeleteForumTag`.
+    /// Returns false if the forum has no such tag.
+    pub fn remove_tag(&mut self, tag_id: Uuid) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|t| t.id != tag_id);
+        if self.tags.len() == before {
+            return false;
+        }
+        for thread in &mut self.threads {
+            thread.tags.retain(|id| *id != tag_id);
+        }
+        true
+    }
+}
+
+pub fn sort_summaries(threads: &mut [ThreadSummary], sort: ThreadSort) {
+    threads.sort_by(|a, b| {
+        b.pinned.cmp(&a.pinned).then_with(|| match sort {
+            ThreadSort::LatestActivity => b.last_post_at.cmp(&a.last_post_at),
+            ThreadSort::Newest => b.timestamp.cmp(&a.timestamp),
+            ThreadSort::MostReplies => b.post_count.cmp(&a.post_count).then(b.last_post_at.cmp(&a.last_post_at)),
+            ThreadSort::TopVoted => b.score.cmp(&a.score).then(b.last_post_at.cmp(&a.last_post_at)),
+        })
+    });
+}
+
+/// One page of results plus the cursor for the next one
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub has_more: bool,
+    pub next_cursor: Option<PaginationCursor>,
+}
+
+/// Slice a listing by cursor. `Offset` and `Start` index into the list;
+/// `Timestamp(t)` resumes after the last item at or before `t`, so it only
+/// makes sense for chronological lists such as thread posts; use
+/// `paginate_listing` for sorted ones. A `limit` of 0 is treated as 1 so every
+/// page makes progress.
+pub fn paginate<T: Clone>(
+    items: &[T],
+    cursor: &PaginationCursor,
+    limit: usize,
+    timestamp_of: impl Fn(&T) -> i64,
+) -> Page<T> {
+    let start = match cursor {
+        PaginationCursor::Start => 0,
+        PaginationCursor::Offset(offset) => (*offset).min(items.len()),
+        PaginationCursor::Timestamp(t) => items.iter().position(|i| timestamp_of(i) > *t).unwrap_or(items.len()),
+    };
+    let end = (start + limit.max(1)).min(items.len());
+    let has_more = end < items.len();
+    Page {
+        items: items[start..end].to_vec(),
+        has_more,
+        next_cursor: has_more.then_some(PaginationCursor::Offset(end)),
+    }
+}
+
+/// `paginate` for listings that aren't chronological, such as thread summaries
+/// sorted by activity or score, where a timestamp can't mark a position
+pub fn paginate_listing<T: Clone>(items: &[T], cursor: &PaginationCursor, limit: usize) -> Result<Page<T>, ForumError> {
+    if let PaginationCursor::Timestamp(_) = cursor {
+        return Err(ForumError::UnsupportedCursor);
+    }
+    Ok(paginate(items, cursor, limit, |_| 0))
+}
+
+/// Forum index in display order: categories by position, forums by position within
+/// their category, uncategorized forums last
+pub fn sort_forum_index(categories: &mut [ForumCategory], forums: &mut [ForumSummary]) {
+    categories.sort_by_key(|c| c.position);
+    let rank = |forum: &ForumSummary| {
+        forum
+            .category_id
+            .and_then(|id| categories.iter().position(|c| c.id == id))
+            .unwrap_or(usize::MAX)
+    };
+    forums.sort_by_key(|f| (rank(f), f.position));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_user;
+    use crate::{ForumTag, UserColor};
+
+    fn post(author: Uuid, content: &str) -> Post {
+        Post {
+            id: Uuid::new_v4(),
+            author: test_user(author),
+            content: content.to_string(),
+            timestamp: 0,
+            reply_to: None,
+            edited_at: None,
+            score: 0,
+        }
+    }
+
+    fn tag(name: &str) -> ForumTag {
+        ForumTag { id: Uuid::new_v4(), name: name.to_string(), color: UserColor::new("Cyan") }
+    }
+
+    fn forum(tags: Vec<ForumTag>, threads: Vec<Thread>) -> Forum {
+        Forum {
+            id: Uuid::new_v4(),
+            name: "General".to_string(),
+            description: String::new(),
+            threads,
+            category_id: None,
+            position: 0,
+            tags,
+        }
+    }
+
+    fn thread(tags: Vec<Uuid>) -> Thread {
+        let author = Uuid::new_v4();
+        Thread {
+            id: Uuid::new_v4(),
+            title: "Question".to_string(),
+            author: test_user(author),
+            posts: vec![post(author, "opening post")],
+            timestamp: 0,
+            edited_at: None,
+            pinned: false,
+            locked: false,
+            tags,
+            accepted_answer: None,
+        }
+    }
+
+    #[test]
+    fn moved_threads_keep_only_tags_the_new_forum_defines() {
+        let (shared, source_only) = (tag("help"), tag("bug"));
+        let moved = thread(vec![shared.id, source_only.id]);
+        let moved_id = moved.id;
+        let mut forums = vec![
+            forum(vec![shared.clone(), source_only.clone()], vec![moved]),
+            forum(vec![shared.clone()], vec![]),
+        ];
+        let (from_id, to_id) = (forums[0].id, forums[1].id);
+
+        assert_eq!(move_thread(&mut forums, moved_id, to_id), Ok(from_id));
+        assert!(forums[0].threads.is_empty());
+        assert_eq!(forums[1].threads[0].tags, vec![shared.id]);
+        assert_eq!(move_thread(&mut forums, Uuid::new_v4(), to_id), Err(ForumError::ThreadNotFound));
+        assert_eq!(move_thread(&mut forums, moved_id, Uuid::new_v4()), Err(ForumError::ForumNotFound));
+    }
+
+    #[test]
+    fn removing_a_tag_untags_every_thread() {
+        let (kept, removed) = (tag("help"), tag("bug"));
+        let mut forum = forum(
+            vec![kept.clone(), removed.clone()],
+            vec![thread(vec![kept.id, removed.id]), thread(vec![removed.id])],
+        );
+
+        assert!(forum.remove_tag(removed.id));
+        assert_eq!(forum.tags.iter().map(|t| t.id).collect::<Vec<_>>(), vec![kept.id]);
+        assert_eq!(forum.threads[0].tags, vec![kept.id]);
+        assert!(forum.threads[1].tags.is_empty());
+        assert!(!forum.remove_tag(removed.id));
+    }
+
+    #[test]
+    fn editing_keeps_the_old_content_as_a_revision() {
+        let author = Uuid::new_v4();
+        let mut post = post(author, "first");
+        assert!(post.edit("first".into(), author, 50).is_none());
+        assert_eq!(post.edited_at, None);
+
+        let revision = post.edit("second".into(), author, 100).unwrap();
+        assert_eq!(post.content, "second");
+        assert_eq!(post.edited_at, Some(100));
+        assert_eq!((revision.post_id, revision.content.as_str()), (post.id, "first"));
+        assert_eq!((revision.edited_by, revision.edited_at), (author, 100));
+    }
+
+    #[test]
+    fn authors_and_moderators_can_edit_and_see_revisions() {
+        let (author, other) = (Uuid::new_v4(), Uuid::new_v4());
+        for check in [can_edit, can_view_revisions] {
+            assert!(check(author, UserRole::User, author));
+            assert!(!check(other, UserRole::User, author));
+            assert!(check(other, UserRole::Moderator, author));
+            assert!(check(other, UserRole::Admin, author));
+        }
+    }
+
+    // Items are their own timestamps
+    fn page(items: &[i64], cursor: PaginationCursor, limit: usize) -> Page<i64> {
+        paginate(items, &cursor, limit, |t| *t)
+    }
+
+    #[test]
+    fn offset_cursor_walks_the_list() {
+        let items = [10, 20, 30, 40, 50];
+        let first = page(&items, PaginationCursor::Start, 2);
+        assert_eq!(first.items, vec![10, 20]);
+        assert!(first.has_more);
+        assert_eq!(first.next_cursor, Some(PaginationCursor::Offset(2)));
+
+        let second = page(&items, first.next_cursor.unwrap(), 2);
+        assert_eq!(second.items, vec![30, 40]);
+        assert_eq!(second.next_cursor, Some(PaginationCursor::Offset(4)));
+    }
+
+    #[test]
+    fn timestamp_cursor_resumes_after_the_timestamp() {
+        let items = [10, 20, 30, 40, 50];
+        let after_20 = page(&items, PaginationCursor::Timestamp(20), 2);
+        assert_eq!(after_20.items, vec![30, 40]);
+        assert_eq!(after_20.next_cursor, Some(PaginationCursor::Offset(4)));
+
+        let after_25 = page(&items, PaginationCursor::Timestamp(25), 10);
+        assert_eq!(after_25.items, vec![30, 40, 50]);
+        assert!(!after_25.has_more);
+    }
+
+    #[test]
+    fn end_of_list_has_no_next_cursor() {
+        let items = [10, 20, 30];
+        let last = page(&items, PaginationCursor::Offset(2), 5);
+        assert_eq!(last.items, vec![30]);
+        assert!(!last.has_more);
+        assert_eq!(last.next_cursor, None);
+
+        for cursor in [PaginationCursor::Offset(3), PaginationCursor::Offset(99), PaginationCursor::Timestamp(30)] {
+            let past = page(&items, cursor, 5);
+            assert!(past.items.is_empty() && !past.has_more && past.next_cursor.is_none());
+        }
+    }
+
+    #[test]
+    fn listings_reject_timestamp_cursors() {
+        let items = [50, 10, 40];
+        let page = paginate_listing(&items, &PaginationCursor::Offset(1), 1).unwrap();
+        assert_eq!(page.items, vec![10]);
+        assert_eq!(page.next_cursor, Some(PaginationCursor::Offset(2)));
+        assert_eq!(
+            paginate_listing(&items, &PaginationCursor::Timestamp(20), 1).unwrap_err(),
+            ForumError::UnsupportedCursor
+        );
+    }
+
+    #[test]
+    fn zero_limit_still_makes_progress() {
+        let items = [10, 20];
+        let first = page(&items, PaginationCursor::Start, 0);
+        assert_eq!(first.items, vec![10]);
+        assert_eq!(first.next_cursor, Some(PaginationCursor::Offset(1)));
+        let second = page(&items, first.next_cursor.unwrap(), 0);
+        assert_eq!(second.items, vec![20]);
+        assert!(!second.has_more);
+    }
+}
@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::{Friendship, FriendshipStatus};
+
+/// Accepted friends of a user
+pub fn friends_of(friendships: &[Friendship], user_id: Uuid) -> HashSet<Uuid> {
+    friendships
+        .iter()
+        .filter(|f| f.status == FriendshipStatus::Accepted)
+        .filter_map(|f| f.other(user_id))
+        .collect()
+}
+
+pub fn are_friends(friendships: &[Friendship], a: Uuid, b: Uuid) -> bool {
+    friendships
+        .iter()
+        .any(|f| f.status == FriendshipStatus::Accepted && f.other(a) == Some(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn friendship(requester: Uuid, addressee: Uuid, status: FriendshipStatus) -> Friendship {
+        Friendship { id: Uuid::new_v4(), requester, addressee, status, created_at: 0, responded_at: None }
+    }
+
+    #[test]
+    fn only_accepted_friendships_count() {
+        let (me, accepted, incoming, pending, declined) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let friendships = vec![
+            friendship(me, accepted, FriendshipStatus::Accepted),
+            friendship(incoming, me, FriendshipStatus::Accepted),
+            friendship(me, pending, FriendshipStatus::Pending),
+            friendship(declined, me, FriendshipStatus::Declined),
+            friendship(accepted, incoming, FriendshipStatus::Accepted),
+        ];
+
+        assert_eq!(friends_of(&friendships, me), HashSet::from([accepted, incoming]));
+        assert!(are_friends(&friendships, me, accepted));
+        assert!(are_friends(&friendships, me, incoming)); // Either direction
+        assert!(are_friends(&friendships, incoming, me));
+        assert!(!are_friends(&friendships, me, pending));
+        assert!(!are_friends(&friendships, me, declined));
+        assert!(!are_friends(&friendships, me, me));
+    }
+}
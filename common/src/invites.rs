@@ -0,0 +1,223 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{InviteLink, ServerInvite, ServerInviteStatus};
+
+/// How long a direct server invite stays pending before it expires
+pub const SERVER_INVITE_TTL_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+/// Characters used in invite codes: URL-safe, and without look-alikes (0/O, 1/I/l)
+const INVITE_CODE_ALPHABET: &[u8] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+pub const INVITE_CODE_LEN: usize = 8;
+
+/// Random invite code such as `k7RmXq2D`, safe to put in a URL path unescaped
+pub fn generate_invite_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..INVITE_CODE_LEN)
+        .map(|_| INVITE_CODE_ALPHABET[rng.gen_range(0..INVITE_CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Why an invite link can't be used
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum InviteLinkError {
+    NotFound,
+    Revoked,
+    Expired,
+    UsedUp,
+    AlreadyMember,
+    Banned,
+}
+
+impl std::fmt::Display for InviteLinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InviteLinkError::NotFound => write!(f, "Invite code not found"),
+            InviteLinkError::Revoked => write!(f, "This invite has been revoked"),
+            InviteLinkError::Expired => write!(f, "This invite has expired"),
+            InviteLinkError::UsedUp => write!(f, "This invite has reached its usage limit"),
+            InviteLinkError::AlreadyMember => write!(f, "You are already a member of this server"),
+            InviteLinkError::Banned => write!(f, "You are banned from this server"),
+        }
+    }
+}
+
+impl InviteLink {
+    /// Check the link is still usable at `now` (unix seconds)
+    pub fn check_usable(&self, now: i64) -> Result<(), InviteLinkError> {
+        if self.revoked {
+            return Err(InviteLinkError::Revoked);
+        }
+        if self.expires_at.is_some_and(|e| now >= e) {
+            return Err(InviteLinkError::Expired);
+        }
+        if self.max_uses.is_some_and(|max| self.uses >= max) {
+            return Err(InviteLinkError::UsedUp);
+        }
+        Ok(())
+    }
+
+    /// Record one use, failing without changes if the link is no longer usable
+    pub fn redeem(&mut self, now: i64) -> Result<(), InviteLinkError> {
+        self.check_usable(now)?;
+        self.uses += 1;
+        Ok(())
+    }
+}
+
+/// Accept codes the way users paste them: surrounding whitespace or a full
+/// `.../invite/<code>` URL, including any query string or fragment
+pub fn normalize_invite_code(input: &str) -> &str {
+    let input = input.trim();
+    let input = input.split(['?', '#']).next().unwrap_or(input).trim_end_matches('/');
+    input.rsplit('/').next().unwrap_or(input)
+}
+
+impl ServerInvite {
+    /// Expiry time for an invite sent at `timestamp` with the default TTL
+    pub fn default_expiry(timestamp: i64) -> i64 {
+        timestamp + SERVER_INVITE_TTL_SECONDS
+    }
+
+    /// When the invite expires. Invites stored before expiry existed have no
+    /// `expires_at` and fall back to the default TTL from when they were sent.
+    pub fn expiry(&self) -> i64 {
+        self.expires_at.unwrap_or_else(|| Self::default_expiry(self.timestamp))
+    }
+
+    pub fn is_expired_at(&self, now: i64) -> bool {
+        now >= self.expiry()
+    }
+
+    /// Move a pending invite to `Expired` once its TTL has passed.
+    /// Returns true if the status changed, so the caller can send `ServerInviteExpired`.
+    pub fn expire_if_due(&mut self, now: i64) -> bool {
+        if self.status == ServerInviteStatus::Pending && self.is_expired_at(now) {
+            self.status = ServerInviteStatus::Expired;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Expire every pending invite whose TTL has passed, returning the ones that changed
+pub fn expire_invites(invites: &mut [ServerInvite], now: i64) -> Vec<&ServerInvite> {
+    invites
+        .iter_mut()
+        .filter_map(|invite| invite.expire_if_due(now).then_some(&*invite))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{test_server, test_user};
+    use uuid::Uuid;
+
+    fn invite(timestamp: i64, expires_at: Option<i64>) -> ServerInvite {
+        let from = Uuid::new_v4();
+        ServerInvite {
+            id: Uuid::new_v4(),
+            from_user: test_user(from),
+            to_user_id: Uuid::new_v4(),
+            server: test_server(from, &[]),
+            timestamp,
+            status: ServerInviteStatus::Pending,
+            expires_at,
+        }
+    }
+
+    fn link(expires_at: Option<i64>, max_uses: Option<u32>) -> InviteLink {
+        InviteLink {
+            code: generate_invite_code(),
+            server_id: Uuid::new_v4(),
+            created_by: Uuid::new_v4(),
+            created_at: 0,
+            expires_at,
+            max_uses,
+            uses: 0,
+            revoked: false,
+        }
+    }
+
+    #[test]
+    fn codes_use_the_alphabet_and_length() {
+        for _ in 0..100 {
+            let code = generate_invite_code();
+            assert_eq!(code.len(), INVITE_CODE_LEN);
+            assert!(code.bytes().all(|b| INVITE_CODE_ALPHABET.contains(&b)), "{}", code);
+        }
+        assert!(!INVITE_CODE_ALPHABET.iter().any(|b| b"01OIl".contains(b)));
+    }
+
+    #[test]
+    fn codes_are_extracted_from_pasted_links() {
+        for input in [
+            "abc",
+            "  abc \n",
+            "https://x/invite/abc",
+            "https://x/invite/abc/",
+            "https://x/invite/abc?ref=1",
+            "https://x/invite/abc/?ref=1#top",
+            "https://x/invite/abc#top",
+        ] {
+            assert_eq!(normalize_invite_code(input), "abc", "{}", input);
+        }
+    }
+
+    #[test]
+    fn revoked_links_are_unusable() {
+        let mut link = link(None, None);
+        link.revoked = true;
+        assert_eq!(link.redeem(0), Err(InviteLinkError::Revoked));
+    }
+
+    #[test]
+    fn links_expire_at_the_boundary() {
+        let link = link(Some(100), None);
+        assert_eq!(link.check_usable(99), Ok(()));
+        assert_eq!(link.check_usable(100), Err(InviteLinkError::Expired));
+    }
+
+    #[test]
+    fn links_run_out_of_uses_and_failures_are_not_counted() {
+        let mut link = link(Some(100), Some(2));
+        assert_eq!(link.redeem(0), Ok(()));
+        assert_eq!(link.redeem(0), Ok(()));
+        assert_eq!(link.redeem(0), Err(InviteLinkError::UsedUp));
+        assert_eq!(link.uses, 2);
+
+        link.max_uses = None;
+        assert_eq!(link.redeem(100), Err(InviteLinkError::Expired));
+        assert_eq!(link.uses, 2);
+    }
+
+    #[test]
+    fn invites_expire_at_their_expiry_time() {
+        let mut invite = invite(1_000, Some(2_000));
+        assert!(!invite.expire_if_due(1_999));
+        assert!(invite.expire_if_due(2_000));
+        assert_eq!(invite.status, ServerInviteStatus::Expired);
+        assert!(!invite.expire_if_due(3_000));
+    }
+
+    #[test]
+    fn invites_without_expiry_use_the_default_ttl() {
+        let mut invite = invite(1_000, None);
+        let expiry = 1_000 + SERVER_INVITE_TTL_SECONDS;
+        assert_eq!(invite.expiry(), expiry);
+        assert!(!invite.expire_if_due(expiry - 1));
+        assert!(invite.expire_if_due(expiry));
+    }
+
+    #[test]
+    fn only_pending_invites_are_expired() {
+        let mut invites = vec![invite(0, Some(10)), invite(0, Some(100)), invite(0, Some(10))];
+        invites[2].status = ServerInviteStatus::Accepted;
+        let expired: Vec<Uuid> = expire_invites(&mut invites, 50).iter().map(|i| i.id).collect();
+        assert_eq!(expired, vec![invites[0].id]);
+        assert_eq!(invites[2].status, ServerInviteStatus::Accepted);
+    }
+}
@@ -0,0 +1,168 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use crate::config::SecurityConfig;
+
+/// A single address or CIDR range, e.g. `192.168.1.10`, `10.0.0.0/8` or `fe80::/10`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.network, addr.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), addr) => {
+                // IPv4 clients are compared by their mapped form against ranges like ::/0
+                let addr = match addr {
+                    IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+                    IpAddr::V6(v6) => v6,
+                };
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let network = IpAddr::from_str(addr).map_err(|_| format!("'{}' is not a valid IP address", s))?;
+        // The prefix is checked against the family as written, so `::ffff:192.168.0.0/112` is valid
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| format!("'{}' has an invalid prefix length (0-{})", s, max))?,
+            None => max,
+        };
+        // IPv4-mapped ranges that stay within the mapped block become plain IPv4 ranges
+        match network.to_canonical() {
+            IpAddr::V4(v4) if network.is_ipv6() && prefix >= 96 => {
+                Ok(Self { network: IpAddr::V4(v4), prefix: prefix - 96 })
+            }
+            _ => Ok(Self { network, prefix }),
+        }
+    }
+}
+
+/// Allow/deny filter built from `SecurityConfig.ip_whitelist` and `ip_blacklist`.
+///
+/// Precedence: the blacklist always wins. If the whitelist is empty every other
+/// address is allowed; otherwise only whitelisted addresses are.
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    whitelist: Vec<IpRange>,
+    blacklist: Vec<IpRange>,
+}
+
+impl IpFilter {
+    pub fn from_config(config: &SecurityConfig) -> Result<Self, String> {
+        Ok(Self {
+            whitelist: parse_list(&config.ip_whitelist, "whitelist")?,
+            blacklist: parse_list(&config.ip_blacklist, "blacklist")?,
+        })
+    }
+
+    pub fn is_allowed(&self, addr: IpAddr) -> bool {
+        if self.blacklist.iter().any(|r| r.contains(addr)) {
+            return false;
+        }
+        self.whitelist.is_empty() || self.whitelist.iter().any(|r| r.contains(addr))
+    }
+}
+
+fn parse_list(entries: &[String], name: &str) -> Result<Vec<IpRange>, String> {
+    entries
+        .iter()
+        .map(|e| e.parse().map_err(|err| format!("Invalid IP {} entry: {}", name, err)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(s: &str) -> IpRange {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn filter(whitelist: &[&str], blacklist: &[&str]) -> IpFilter {
+        let list = |entries: &[&str]| entries.iter().map(|e| range(e)).collect();
+        IpFilter { whitelist: list(whitelist), blacklist: list(blacklist) }
+    }
+
+    #[test]
+    fn ipv4_cidr_matching() {
+        let net = range("10.1.0.0/16");
+        assert!(net.contains(ip("10.1.255.3")));
+        assert!(!net.contains(ip("10.2.0.1")));
+        assert!(range("192.168.1.10").contains(ip("192.168.1.10")));
+        assert!(!range("192.168.1.10").contains(ip("192.168.1.11")));
+        assert!(range("0.0.0.0/0").contains(ip("8.8.8.8")));
+    }
+
+    #[test]
+    fn ipv6_cidr_matching() {
+        let net = range("fe80::/10");
+        assert!(net.contains(ip("fe80::1")));
+        assert!(net.contains(ip("febf::1")));
+        assert!(!net.contains(ip("fec0::1")));
+        assert!(!net.contains(ip("10.0.0.1")));
+        assert!(range("::/0").contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_and_ranges() {
+        assert!(range("192.168.0.0/16").contains(ip("::ffff:192.168.3.4")));
+        let mapped = range("::ffff:192.168.0.0/112");
+        assert_eq!(mapped, range("192.168.0.0/16"));
+        assert!(mapped.contains(ip("192.168.7.7")));
+        assert!(!mapped.contains(ip("192.169.0.1")));
+        assert!(range("::/0").contains(ip("1.2.3.4")));
+    }
+
+    #[test]
+    fn blacklist_wins_over_whitelist() {
+        let filter = filter(&["10.0.0.0/8"], &["10.0.0.5"]);
+        assert!(filter.is_allowed(ip("10.0.0.4")));
+        assert!(!filter.is_allowed(ip("10.0.0.5")));
+        assert!(!filter.is_allowed(ip("11.0.0.1")));
+    }
+
+    #[test]
+    fn empty_whitelist_allows_everything_not_blacklisted() {
+        let filter = filter(&[], &["2001:db8::/32"]);
+        assert!(filter.is_allowed(ip("1.2.3.4")));
+        assert!(filter.is_allowed(ip("2001:db9::1")));
+        assert!(!filter.is_allowed(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn malformed_entries_are_rejected() {
+        for entry in ["10.0.0.0/33", "::1/129", "::ffff:1.2.3.4/129", "10.0.0/8", "10.0.0.0/x", "localhost", ""] {
+            assert!(entry.parse::<IpRange>().is_err(), "{} should be rejected", entry);
+        }
+        let err = parse_list(&["10.0.0.0/8".to_string(), "nope".to_string()], "whitelist").unwrap_err();
+        assert!(err.contains("whitelist") && err.contains("nope"), "{}", err);
+    }
+}
@@ -1,10 +1,41 @@
 // common/src/lib.rs
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
 use uuid::Uuid;
 
 pub mod config;
 pub use config::{ServerConfig, ClientConfig};
+pub mod moderation;
+pub use moderation::{Moderator, ModerationVerdict};
+pub mod rate_limit;
+pub use rate_limit::{RateLimiter, RateLimitCategory, RateLimitKey};
+pub mod ip_filter;
+pub use ip_filter::IpFilter;
+pub mod password;
+pub use password::{PasswordPolicy, PasswordIssue};
+#[cfg(feature = "crypto")]
+pub mod crypto;
+#[cfg(feature = "crypto")]
+pub mod totp;
+#[cfg(feature = "crypto")]
+pub use totp::Totp;
+pub mod validation;
+pub mod presence;
+pub mod friends;
+pub use friends::{friends_of, are_friends};
+pub mod blocking;
+pub use blocking::BlockList;
+pub mod invites;
+pub mod membership;
+pub mod forum;
+pub mod subscriptions;
+pub use subscriptions::ThreadWatches;
+pub mod votes;
+pub use votes::PostVotes;
+#[cfg(test)]
+mod test_support;
 
 // Simple color representation that works for both client and server
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -104,14 +135,106 @@ pub struct User {
     pub profile_pic: Option<String>,
     pub cover_banner: Option<String>,
     pub status: UserStatus,
+    #[serde(default)]
+    pub presence: Option<Presence>,
 }
 
-/// Complete user profile with all fields (for profile editing/viewing)
+/// Custom status set alongside `UserStatus`, e.g. "🎧 In a meeting until 3pm"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Presence {
+    pub custom_text: Option<String>,
+    pub emoji: Option<String>,
+    pub expires_at: Option<i64>, // Cleared automatically after this time
+}
+
+impl Presence {
+    pub fn is_expired_at(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|e| now >= e)
+    }
+}
+
+/// Server-internal account record, including the password hash.
+/// Deliberately not `Serialize` so it can never be sent to a client; convert it
+/// with `profile()` before replying to `GetProfile`.
+///
+/// `ServerMessage` derives `Serialize`, so no variant can hold a `UserAccount`.
+/// This stops compiling if someone adds the derive:
+///
+/// ```compile_fail
+/// fn assert_serialize<T: serde::Serialize>() {}
+/// assert_serialize::<nexus_tui_common::UserAccount>();
+/// ```
+#[derive(Clone)]
+pub struct UserAccount {
+    pub id: Uuid,
+    pub username: String,
+    pub hash: String, // PHC string from `crypto::hash_password`
+    pub color: UserColor,
+    pub role: UserRole,
+    pub bio: Option<String>,
+    pub url1: Option<String>,
+    pub url2: Option<String>,
+    pub url3: Option<String>,
+    pub location: Option<String>,
+    pub profile_pic: Option<String>,
+    pub cover_banner: Option<String>,
+    pub deletion_scheduled_at: Option<i64>, // Account is purged at this time unless the user logs back in and cancels
+}
+
+impl UserAccount {
+    pub fn profile(&self) -> UserProfile {
+        UserProfile {
+            id: self.id,
+            username: self.username.clone(),
+            color: self.color.clone(),
+            role: self.role,
+            bio: self.bio.clone(),
+            url1: self.url1.clone(),
+            url2: self.url2.clone(),
+            url3: self.url3.clone(),
+            location: self.location.clone(),
+            profile_pic: self.profile_pic.clone(),
+            cover_banner: self.cover_banner.clone(),
+        }
+    }
+}
+
+// Manual impl so the hash never ends up in logs
+impl std::fmt::Debug for UserAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserAccount")
+            .field("id", &self.id)
+            .field("username", &self.username)
+            .field("hash", &"<redacted>")
+            .field("role", &self.role)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<UserAccount> for UserProfile {
+    fn from(account: UserAccount) -> Self {
+        Self {
+            id: account.id,
+            username: account.username,
+            color: account.color,
+            role: account.role,
+            bio: account.bio,
+            url1: account.url1,
+            url2: account.url2,
+            url3: account.url3,
+            location: account.location,
+            profile_pic: account.profile_pic,
+            cover_banner: account.cover_banner,
+        }
+    }
+}
+
+/// Public user profile with all displayable fields (for profile editing/viewing).
+/// Carries no credentials; the hash lives only in `UserAccount`.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UserProfile {
     pub id: Uuid,
     pub username: String,
-    pub hash: String, // Password hash (server-side only)
     pub color: UserColor,
     pub role: UserRole,
     pub bio: Option<String>,
@@ -131,6 +254,8 @@ pub struct UserInfo {
     pub color: UserColor,
     pub role: UserRole,
     pub status: UserStatus,
+    #[serde(default)]
+    pub presence: Option<Presence>,
 }
 
 impl From<User> for UserInfo {
@@ -141,6 +266,7 @@ impl From<User> for UserInfo {
             color: user.color,
             role: user.role,
             status: user.status,
+            presence: user.presence,
         }
     }
 }
@@ -153,10 +279,113 @@ impl From<&User> for UserInfo {
             color: user.color.clone(),
             role: user.role,
             status: user.status, // Now UserStatus implements Copy, so no clone needed
+            presence: user.presence.clone(),
+        }
+    }
+}
+
+// --- Account Lifecycle ---
+
+/// Version of the `AccountExport` archive layout, bumped on breaking changes
+pub const ACCOUNT_EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// Everything a user owns, returned by `ExportMyData`.
+///
+/// Serialized as a single JSON object:
+/// - `format_version`: `ACCOUNT_EXPORT_FORMAT_VERSION`
+/// - `exported_at`: unix seconds
+/// - `profile`: every `UserProfile` field
+/// - `threads`, `posts`: forum content authored by the user
+/// - `direct_messages`: DMs sent or received by the user
+/// - `channel_messages`: channel messages sent by the user
+/// - `notifications`: the user's notifications, read or not
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountExport {
+    pub format_version: u32,
+    pub exported_at: i64,
+    pub profile: UserProfile,
+    pub threads: Vec<ExportedThread>,
+    pub posts: Vec<ExportedPost>,
+    pub direct_messages: Vec<DirectMessage>,
+    pub channel_messages: Vec<ChannelMessage>,
+    pub notifications: Vec<Notification>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedThread {
+    pub id: Uuid,
+    pub forum_id: Uuid,
+    pub title: String,
+    pub timestamp: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedPost {
+    pub id: Uuid,
+    pub thread_id: Uuid,
+    pub content: String,
+    pub timestamp: i64,
+    pub reply_to: Option<Uuid>,
+}
+
+// --- Friends ---
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FriendshipStatus {
+    Pending,
+    Accepted,
+    Declined,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Friendship {
+    pub id: Uuid,
+    pub requester: Uuid,
+    pub addressee: Uuid,
+    pub status: FriendshipStatus,
+    pub created_at: i64,
+    pub responded_at: Option<i64>,
+}
+
+impl Friendship {
+    pub fn involves(&self, user_id: Uuid) -> bool {
+        self.requester == user_id || self.addressee == user_id
+    }
+
+    /// The other side of the friendship, if `user_id` is part of it
+    pub fn other(&self, user_id: Uuid) -> Option<Uuid> {
+        if self.requester == user_id {
+            Some(self.addressee)
+        } else if self.addressee == user_id {
+            Some(self.requester)
+        } else {
+            None
         }
     }
 }
 
+/// Who can see a user's status and custom presence
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PresenceVisibility {
+    #[default]
+    Everyone, // Anyone sharing a server
+    FriendsOnly,
+    Nobody, // Always appear offline
+}
+
+// --- Sessions ---
+
+/// One logged-in connection of the current user
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionInfo {
+    pub id: Uuid,
+    pub client_name: Option<String>,
+    pub ip: IpAddr,
+    pub created_at: i64,
+    pub last_seen: i64,
+    pub current: bool, // True for the connection that asked for the list
+}
+
 // --- Data Structures ---
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -165,6 +394,27 @@ pub struct Forum {
     pub name: String,
     pub description: String,
     pub threads: Vec<Thread>,
+    #[serde(default)]
+    pub category_id: Option<Uuid>, // None = uncategorized, listed last
+    #[serde(default)]
+    pub position: u32, // Order within the category, lowest first
+    #[serde(default)]
+    pub tags: Vec<ForumTag>, // Tags threads in this forum may carry
+}
+
+/// Heading that groups forums in the index
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForumCategory {
+    pub id: Uuid,
+    pub name: String,
+    pub position: u32, // Lowest first
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ForumTag {
+    pub id: Uuid,
+    pub name: String,
+    pub color: UserColor,
 }
 
 /// Lightweight forum structure for initial loading (no profile images)
@@ -174,6 +424,12 @@ pub struct ForumLightweight {
     pub name: String,
     pub description: String,
     pub threads: Vec<ThreadLightweight>,
+    #[serde(default)]
+    pub category_id: Option<Uuid>,
+    #[serde(default)]
+    pub position: u32,
+    #[serde(default)]
+    pub tags: Vec<ForumTag>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -183,6 +439,16 @@ pub struct Thread {
     pub author: User,
     pub posts: Vec<Post>,
     pub timestamp: i64,
+    #[serde(default)]
+    pub edited_at: Option<i64>, // Last title edit
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub locked: bool, // No new posts or replies
+    #[serde(default)]
+    pub tags: Vec<Uuid>, // Ids from the forum's `tags`
+    #[serde(default)]
+    pub accepted_answer: Option<Uuid>, // Post marked as solving the thread
 }
 
 /// Lightweight thread structure for initial loading (no profile images)
@@ -193,6 +459,16 @@ pub struct ThreadLightweight {
     pub author: UserInfo,
     pub posts: Vec<PostLightweight>,
     pub timestamp: i64,
+    #[serde(default)]
+    pub edited_at: Option<i64>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub locked: bool,
+    #[serde(default)]
+    pub tags: Vec<Uuid>,
+    #[serde(default)]
+    pub accepted_answer: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -202,6 +478,16 @@ pub struct Post {
     pub content: String,
     pub timestamp: i64,
     pub reply_to: Option<Uuid>,
+    #[serde(default)]
+    pub edited_at: Option<i64>, // None if never edited
+    #[serde(default)]
+    pub score: i64, // Upvotes minus downvotes; who voted how stays server-side in votes::PostVotes
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Vote {
+    Up,
+    Down,
 }
 
 /// Lightweight post structure for initial loading (no profile images)
@@ -212,6 +498,82 @@ pub struct PostLightweight {
     pub content: String,
     pub timestamp: i64,
     pub reply_to: Option<Uuid>,
+    #[serde(default)]
+    pub edited_at: Option<i64>,
+    #[serde(default)]
+    pub score: i64,
+    #[serde(default)]
+    pub my_vote: Option<Vote>, // The requesting user's vote
+}
+
+/// Forum index entry without any threads
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForumSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub description: String,
+    pub thread_count: usize,
+    pub last_post_at: Option<i64>,
+    pub category_id: Option<Uuid>,
+    pub position: u32,
+    pub tags: Vec<ForumTag>,
+}
+
+/// Thread listing entry without any post bodies
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThreadSummary {
+    pub id: Uuid,
+    pub forum_id: Uuid,
+    pub title: String,
+    pub author: UserInfo,
+    pub timestamp: i64,
+    pub post_count: usize,
+    pub last_post_at: i64, // Thread timestamp if it has no posts
+    pub last_author: Option<UserInfo>,
+    pub edited_at: Option<i64>,
+    pub pinned: bool,
+    pub locked: bool,
+    pub tags: Vec<Uuid>,
+    pub score: i64, // Score of the opening post
+    pub solved: bool, // Has an accepted answer
+}
+
+/// Order of thread listings
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThreadSort {
+    #[default]
+    LatestActivity,
+    Newest,
+    MostReplies,
+    TopVoted,
+}
+
+/// Previous version of a post, stored each time it is edited
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PostRevision {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub content: String, // Content as it was before this edit
+    pub edited_by: Uuid, // Author or the moderator who made the edit
+    pub edited_at: i64,
+}
+
+/// Which posts in a thread notify a user
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum WatchLevel {
+    AllPosts,
+    RepliesToMe, // Replies to the user's posts, and top-level posts if they started the thread
+    Muted, // Never notified, and posting doesn't re-subscribe
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThreadWatch {
+    pub thread_id: Uuid,
+    pub level: WatchLevel,
+}
+
+fn default_subscribe_level() -> WatchLevel {
+    WatchLevel::AllPosts
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -227,13 +589,56 @@ pub struct Server {
     pub name: String,
     pub description: String,
     pub public: bool,
-    pub invite_code: Option<String>,
+    pub invite_code: Option<String>, // Legacy permanent code, prefer InviteLink
     pub icon: Option<String>, // base64
     pub banner: Option<String>, // base64
     pub owner: Uuid,
     pub mods: Vec<Uuid>,
     pub userlist: Vec<Uuid>,
     pub channels: Vec<Channel>,
+    #[serde(default)]
+    pub roles: Vec<ServerRole>,
+    #[serde(default)]
+    pub role_assignments: HashMap<Uuid, Vec<Uuid>>, // user_id -> role ids
+    #[serde(default)]
+    pub tags: Vec<String>, // Categories shown in discovery, see validation::normalize_server_tags
+}
+
+/// Public server listing entry, without channels, member ids or image data
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub description: String,
+    pub member_count: usize,
+    pub icon_ref: Option<Uuid>, // Fetch the icon with GetServerIcons, None if the server has no icon
+    pub tags: Vec<String>,
+}
+
+impl Server {
+    pub fn summary(&self) -> ServerSummary {
+        ServerSummary {
+            id: self.id,
+            name: self.name.clone(),
+            description: self.description.clone(),
+            member_count: self.userlist.len(),
+            icon_ref: self.icon.as_ref().map(|_| self.id),
+            tags: self.tags.clone(),
+        }
+    }
+
+    /// Discovery filter: public servers whose name, description or tags contain the query
+    pub fn matches_discovery(&self, query: Option<&str>) -> bool {
+        if !self.public {
+            return false;
+        }
+        let Some(query) = query.map(|q| q.trim().to_lowercase()).filter(|q| !q.is_empty()) else {
+            return true;
+        };
+        self.name.to_lowercase().contains(&query)
+            || self.description.to_lowercase().contains(&query)
+            || self.tags.iter().any(|t| t.contains(&query))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -291,6 +696,238 @@ pub struct Notification {
     pub extra: Option<String>,
 }
 
+// --- Server Roles ---
+
+/// Custom role defined by a server owner, e.g. "Reviewers" or "Ops"
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ServerRole {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub name: String,
+    pub color: UserColor,
+    pub position: u32, // Higher position outranks lower ones
+    pub permissions: ServerPermissions,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServerPermissions {
+    pub manage_server: bool,
+    pub manage_roles: bool,
+    pub manage_channels: bool,
+    pub manage_messages: bool,
+    pub kick_members: bool,
+    pub ban_members: bool,
+    pub mute_members: bool,
+    pub create_invites: bool,
+}
+
+impl ServerPermissions {
+    pub fn all() -> Self {
+        Self {
+            manage_server: true,
+            manage_roles: true,
+            manage_channels: true,
+            manage_messages: true,
+            kick_members: true,
+            ban_members: true,
+            mute_members: true,
+            create_invites: true,
+        }
+    }
+
+    /// Combine two permission sets, granting anything either one grants
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            manage_server: self.manage_server || other.manage_server,
+            manage_roles: self.manage_roles || other.manage_roles,
+            manage_channels: self.manage_channels || other.manage_channels,
+            manage_messages: self.manage_messages || other.manage_messages,
+            kick_members: self.kick_members || other.kick_members,
+            ban_members: self.ban_members || other.ban_members,
+            mute_members: self.mute_members || other.mute_members,
+            create_invites: self.create_invites || other.create_invites,
+        }
+    }
+
+    /// Whether every permission granted by `other` is also granted by `self`
+    pub fn contains(self, other: Self) -> bool {
+        self.union(other) == self
+    }
+}
+
+impl ServerRole {
+    pub fn outranks(&self, other: &ServerRole) -> bool {
+        self.position > other.position
+    }
+}
+
+impl Server {
+    /// Roles currently assigned to a member, highest position first
+    pub fn member_roles(&self, user_id: Uuid) -> Vec<&ServerRole> {
+        let mut roles: Vec<&ServerRole> = match self.role_assignments.get(&user_id) {
+            Some(ids) => self.roles.iter().filter(|r| ids.contains(&r.id)).collect(),
+            None => Vec::new(),
+        };
+        roles.sort_by_key(|r| std::cmp::Reverse(r.position));
+        roles
+    }
+
+    /// Highest role position held by a member, or None if they have no roles
+    pub fn highest_role_position(&self, user_id: Uuid) -> Option<u32> {
+        self.member_roles(user_id).first().map(|r| r.position)
+    }
+
+    /// Effective permissions of a member. The owner always has every permission.
+    pub fn member_permissions(&self, user_id: Uuid) -> ServerPermissions {
+        if user_id == self.owner {
+            return ServerPermissions::all();
+        }
+        self.member_roles(user_id)
+            .into_iter()
+            .fold(ServerPermissions::default(), |acc, r| acc.union(r.permissions))
+    }
+
+    /// Hierarchy rule: a member may only create, edit, assign or delete roles
+    /// strictly below their own highest role, and needs `manage_roles` to do so.
+    pub fn can_manage_role(&self, actor: Uuid, role: &ServerRole) -> bool {
+        role.server_id == self.id && self.can_grant_position(actor, role.position)
+    }
+
+    /// Whether `actor` may give a role these settings, for `CreateServerRole` and
+    /// `UpdateServerRole`: the position must stay below their own highest role and
+    /// the permissions must be ones they hold themselves.
+    pub fn can_grant_role_settings(&self, actor: Uuid, position: u32, permissions: ServerPermissions) -> bool {
+        self.can_grant_position(actor, position) && self.member_permissions(actor).contains(permissions)
+    }
+
+    /// Full check for `UpdateServerRole`: the role as it is now and as it would be
+    /// after the update must both be manageable by `actor`
+    pub fn can_update_role(
+        &self,
+        actor: Uuid,
+        role: &ServerRole,
+        position: Option<u32>,
+        permissions: Option<ServerPermissions>,
+    ) -> bool {
+        self.can_manage_role(actor, role)
+            && self.can_grant_role_settings(
+                actor,
+                position.unwrap_or(role.position),
+                permissions.unwrap_or(role.permissions),
+            )
+    }
+
+    fn can_grant_position(&self, actor: Uuid, position: u32) -> bool {
+        if actor == self.owner {
+            return true;
+        }
+        if !self.member_permissions(actor).manage_roles {
+            return false;
+        }
+        self.highest_role_position(actor).is_some_and(|pos| pos > position)
+    }
+
+    /// Check for `AssignServerRole` and `UnassignServerRole`: besides managing the role,
+    /// a non-owner must outrank the member whose roles change
+    pub fn can_assign_role(&self, actor: Uuid, target: Uuid, role: &ServerRole) -> bool {
+        self.can_manage_role(actor, role)
+            && self.is_member(target)
+            && (actor == self.owner || self.outranks_member(actor, target))
+    }
+
+    /// Whether `actor` sits above `target` in the role hierarchy (e.g. for moderation)
+    pub fn outranks_member(&self, actor: Uuid, target: Uuid) -> bool {
+        if target == self.owner {
+            return false;
+        }
+        if actor == self.owner {
+            return true;
+        }
+        match (self.highest_role_position(actor), self.highest_role_position(target)) {
+            (Some(a), Some(t)) => a > t,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+}
+
+// --- Moderation Sanctions ---
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SanctionKind {
+    Kick,    // One-off removal, the user may rejoin
+    Ban,     // Cannot rejoin the server until expiry
+    Mute,    // Cannot write in one channel until expiry
+    Timeout, // Cannot write anywhere on the server until expiry
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Sanction {
+    pub id: Uuid,
+    pub kind: SanctionKind,
+    pub user_id: Uuid,
+    pub issued_by: Uuid,
+    pub server_id: Uuid,
+    pub channel_id: Option<Uuid>, // Only set for channel mutes
+    pub reason: Option<String>,
+    pub created_at: i64,
+    pub expires_at: Option<i64>, // None = permanent
+    pub lifted: bool,
+}
+
+impl Sanction {
+    /// Whether this sanction still applies at `now` (unix seconds).
+    /// Kicks take effect once and are never considered active.
+    pub fn is_active_at(&self, now: i64) -> bool {
+        if self.lifted || self.kind == SanctionKind::Kick || now < self.created_at {
+            return false;
+        }
+        match self.expires_at {
+            Some(expires_at) => now < expires_at,
+            None => true,
+        }
+    }
+
+    /// Seconds left before expiry, None for permanent or inactive sanctions
+    pub fn remaining_at(&self, now: i64) -> Option<i64> {
+        if !self.is_active_at(now) {
+            return None;
+        }
+        self.expires_at.map(|e| e - now)
+    }
+}
+
+/// Find the sanction currently restricting `user_id` in the given scope.
+/// A channel mute only matches its channel; bans and timeouts cover the whole server.
+pub fn active_sanction(
+    sanctions: &[Sanction],
+    user_id: Uuid,
+    kind: SanctionKind,
+    server_id: Uuid,
+    channel_id: Option<Uuid>,
+    now: i64,
+) -> Option<&Sanction> {
+    sanctions.iter().find(|s| {
+        s.user_id == user_id
+            && s.kind == kind
+            && s.server_id == server_id
+            && (s.kind != SanctionKind::Mute || s.channel_id == channel_id)
+            && s.is_active_at(now)
+    })
+}
+
+/// Whether the user is currently sanctioned with `kind` in the given scope
+pub fn is_sanctioned(
+    sanctions: &[Sanction],
+    user_id: Uuid,
+    kind: SanctionKind,
+    server_id: Uuid,
+    channel_id: Option<Uuid>,
+    now: i64,
+) -> bool {
+    active_sanction(sanctions, user_id, kind, server_id, channel_id, now).is_some()
+}
+
 // --- Server Invites ---
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -301,6 +938,8 @@ pub struct ServerInvite {
     pub server: Server,
     pub timestamp: i64,
     pub status: ServerInviteStatus,
+    #[serde(default)]
+    pub expires_at: Option<i64>, // None on invites stored before expiry existed, see ServerInvite::expiry
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -311,6 +950,19 @@ pub enum ServerInviteStatus {
     Expired,
 }
 
+/// Shareable invite code, usable by anyone who has it (unlike `ServerInvite`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InviteLink {
+    pub code: String, // From `invites::generate_invite_code`
+    pub server_id: Uuid,
+    pub created_by: Uuid,
+    pub created_at: i64,
+    pub expires_at: Option<i64>, // None = never expires
+    pub max_uses: Option<u32>,   // None = unlimited
+    pub uses: u32,
+    pub revoked: bool,
+}
+
 
 // --- Network Protocol Definitions ---
 
@@ -318,8 +970,23 @@ pub enum ServerInviteStatus {
 pub enum ClientMessage {
     // Auth
     Register { username: String, password: String },
-    Login { username: String, password: String },
+    Login {
+        username: String,
+        password: String,
+        #[serde(default)]
+        client_name: Option<String>, // e.g. "nexus-tui-client 0.1.0 (linux)", shown in the session list
+    },
     Logout,
+    // Sessions
+    GetSessions,
+    RevokeSession { id: Uuid },
+    RevokeAllOtherSessions,
+    // Two-factor authentication
+    SubmitTotp { challenge_id: Uuid, code: String }, // Answer to TwoFactorRequired
+    SubmitRecoveryCode { challenge_id: Uuid, code: String }, // Fallback when the authenticator is lost
+    BeginTotpEnrollment,
+    ConfirmTotpEnrollment { code: String }, // Proves the authenticator was set up before 2FA is enforced
+    DisableTotp { password: String, code: String },
     // User
     UpdatePassword(String),
     UpdateColor(UserColor), // Changed from SerializableColor to UserColor
@@ -332,13 +999,52 @@ pub enum ClientMessage {
         profile_pic: Option<String>,
         cover_banner: Option<String>,
     },
+    ChangeUsername { new_username: String, password: String },
+    SetStatus { status: UserStatus, custom_text: Option<String>, emoji: Option<String>, expires_at: Option<i64> },
+    SetPresenceVisibility(PresenceVisibility),
+    // Account lifecycle
+    DeleteAccount { password: String }, // Schedules deletion after the grace period
+    CancelAccountDeletion,
+    ExportMyData,
     // Forums
-    GetForums,
+    GetForums, // Everything at once; prefer GetForumIndex + GetForumThreads + GetThreadPosts
+    GetForumIndex,
+    GetForumThreads {
+        forum_id: Uuid,
+        cursor: PaginationCursor, // Start or Offset; sorted listings can't resume from a timestamp
+        sort: ThreadSort,
+        #[serde(default)]
+        tag: Option<Uuid>, // Only threads carrying this tag
+    },
+    GetThreadPosts { thread_id: Uuid, cursor: PaginationCursor },
     CreateForum { name: String, description: String },
+    CreateForumCategory { name: String },
+    DeleteForumCategory { category_id: Uuid }, // Its forums become uncategorized
+    ReorderForumCategories { order: Vec<Uuid> },
+    SetForumCategory { forum_id: Uuid, category_id: Option<Uuid>, position: u32 },
+    CreateForumTag { forum_id: Uuid, name: String, color: UserColor },
+    DeleteForumTag { forum_id: Uuid, tag_id: Uuid },
+    SetThreadTags { thread_id: Uuid, tag_ids: Vec<Uuid> },
     DeleteForum { forum_id: Uuid },
     CreateThread { forum_id: Uuid, title: String, content: String },
     CreatePost { thread_id: Uuid, content: String },
     CreatePostReply { thread_id: Uuid, content: String, reply_to: Uuid }, // New: Reply to specific post
+    EditPost { post_id: Uuid, content: String },
+    EditThreadTitle { thread_id: Uuid, title: String },
+    GetPostRevisions { post_id: Uuid }, // Author and moderators only
+    // Thread subscriptions
+    SubscribeThread {
+        thread_id: Uuid,
+        #[serde(default = "default_subscribe_level")]
+        level: WatchLevel, // Defaults to AllPosts; see subscriptions::DEFAULT_WATCH_LEVEL for unsubscribed threads
+    },
+    UnsubscribeThread { thread_id: Uuid }, // Back to the default: only replies to me
+    MuteThread { thread_id: Uuid },
+    GetWatchedThreads,
+    // Voting and answers
+    VotePost { post_id: Uuid, vote: Option<Vote> }, // None withdraws the vote
+    MarkAcceptedAnswer { thread_id: Uuid, post_id: Uuid }, // Thread author and moderators only
+    ClearAcceptedAnswer { thread_id: Uuid },
     // Chat
     SendDirectMessage { to: Uuid, content: String },
     SendChannelMessage { channel_id: Uuid, content: String },
@@ -348,13 +1054,58 @@ pub enum ClientMessage {
     // New: Accept/decline invite from a specific user (for DM commands)
     AcceptServerInviteFromUser { from_user_id: Uuid },
     DeclineServerInviteFromUser { from_user_id: Uuid },
+    // Invite links
+    CreateInviteLink { server_id: Uuid, expires_in: Option<i64>, max_uses: Option<u32> }, // expires_in in seconds
+    RevokeInviteLink { code: String },
+    GetInviteLinks { server_id: Uuid },
+    JoinByInviteCode { code: String },
     // Moderation
     DeletePost(Uuid),
     DeleteThread(Uuid),
+    PinThread { thread_id: Uuid, pinned: bool },
+    LockThread { thread_id: Uuid, locked: bool },
+    MoveThread { thread_id: Uuid, to_forum_id: Uuid },
+    KickFromServer { server_id: Uuid, user_id: Uuid, reason: Option<String> },
+    BanFromServer { server_id: Uuid, user_id: Uuid, duration: Option<u64>, reason: Option<String> }, // duration in seconds, None = permanent
+    MuteInChannel { channel_id: Uuid, user_id: Uuid, duration: Option<u64>, reason: Option<String> },
+    Timeout { server_id: Uuid, user_id: Uuid, duration: u64, reason: Option<String> },
+    LiftSanction { sanction_id: Uuid },
+    GetServerSanctions { server_id: Uuid },
     // User management
     GetUserList, // Request the list of connected users
     GetProfile { user_id: Uuid },
     GetServers, // Request all servers the user is a member of
+    // Discovery
+    DiscoverServers { query: Option<String>, cursor: PaginationCursor },
+    JoinPublicServer { server_id: Uuid },
+    SetServerTags { server_id: Uuid, tags: Vec<String> },
+    GetServerIcons { server_ids: Vec<Uuid> },
+    // Server membership
+    LeaveServer { server_id: Uuid },
+    TransferServerOwnership { server_id: Uuid, new_owner: Uuid, confirm_name: String }, // confirm_name must equal the server name
+    DeleteServer { server_id: Uuid, confirm_name: String },
+    // Server roles
+    GetServerRoles { server_id: Uuid },
+    CreateServerRole { server_id: Uuid, name: String, color: UserColor, position: u32, permissions: ServerPermissions },
+    UpdateServerRole {
+        role_id: Uuid,
+        name: Option<String>,
+        color: Option<UserColor>,
+        position: Option<u32>,
+        permissions: Option<ServerPermissions>,
+    },
+    DeleteServerRole { role_id: Uuid },
+    AssignServerRole { server_id: Uuid, user_id: Uuid, role_id: Uuid },
+    UnassignServerRole { server_id: Uuid, user_id: Uuid, role_id: Uuid },
+    // Friends
+    SendFriendRequest { user_id: Uuid },
+    RespondFriendRequest { request_id: Uuid, accept: bool },
+    RemoveFriend { user_id: Uuid },
+    GetFriends,
+    // Blocking
+    BlockUser { user_id: Uuid },
+    UnblockUser { user_id: Uuid },
+    GetBlockedUsers,
     // --- ENHANCED PAGINATION SUPPORT ---
     GetChannelMessagesPaginated { 
         channel_id: Uuid, 
@@ -403,25 +1154,91 @@ pub enum ServerMessage {
     // Auth
     AuthSuccess(User),
     AuthFailure(String),
+    TwoFactorRequired { challenge_id: Uuid }, // Password accepted, send SubmitTotp to finish logging in
+    TotpEnrollment { otpauth_uri: String, recovery_codes: Vec<String> }, // Recovery codes are only ever sent once
+    TotpEnabled,
+    TotpDisabled,
+    // Sessions
+    Sessions(Vec<SessionInfo>),
+    SessionRevoked { id: Uuid }, // Sent to the revoked connection before it is closed, and to the requester
     // General
     Forums(Vec<Forum>),
     ForumsLightweight(Vec<ForumLightweight>), // Lightweight version without profile images
+    ForumIndex { categories: Vec<ForumCategory>, forums: Vec<ForumSummary> },
+    ForumCategories(Vec<ForumCategory>), // After create/delete/reorder, in display order
+    ForumTagsUpdated { forum_id: Uuid, tags: Vec<ForumTag> },
+    ThreadTagsUpdated { thread_id: Uuid, tag_ids: Vec<Uuid> },
+    ForumThreads { forum_id: Uuid, threads: Vec<ThreadSummary>, has_more: bool, next_cursor: Option<PaginationCursor> },
+    ThreadPosts { thread_id: Uuid, posts: Vec<PostLightweight>, has_more: bool, next_cursor: Option<PaginationCursor> },
+    PostEdited { thread_id: Uuid, post_id: Uuid, content: String, edited_at: i64 },
+    ThreadTitleEdited { thread_id: Uuid, title: String, edited_at: i64 },
+    PostRevisions { post_id: Uuid, revisions: Vec<PostRevision> }, // Oldest first
+    ThreadPinned { thread_id: Uuid, pinned: bool },
+    ThreadLocked { thread_id: Uuid, locked: bool },
+    ThreadMoved { thread_id: Uuid, from_forum_id: Uuid, to_forum_id: Uuid },
+    ThreadWatchUpdated { thread_id: Uuid, level: Option<WatchLevel> }, // None = no explicit setting
+    WatchedThreads(Vec<ThreadWatch>),
+    PostScoreUpdated { thread_id: Uuid, post_id: Uuid, score: i64 },
+    AcceptedAnswerChanged { thread_id: Uuid, post_id: Option<Uuid> },
     NewChatMessage(ChatMessage),
     DirectMessage(DirectMessage),
     MentionNotification { from: User, content: String },
     ForumReplyNotification { thread_id: Uuid, from_username: String, message: String, from_user_profile_pic: Option<String> },
     Notification(String, bool), // Message, is_error
     // Server invites
-    ServerInviteReceived(ServerInvite),
+    ServerInviteReceived(Box<ServerInvite>),
     ServerInviteResponse { invite_id: Uuid, accepted: bool, user: User },
+    ServerInviteExpired { invite_id: Uuid, server_id: Uuid }, // Sent to both sender and recipient
+    // Invite links
+    InviteLinkCreated(InviteLink),
+    InviteLinkRevoked { code: String },
+    InviteLinks { server_id: Uuid, links: Vec<InviteLink> },
+    InviteLinkRejected { code: String, reason: invites::InviteLinkError },
+    JoinedServer(Box<Server>),
     // User management
     UserList(Vec<User>), // List of connected users
     UserJoined(User),    // A user joined
     UserLeft(Uuid),      // A user left (by id)
+    PresenceUpdated { user_id: Uuid, status: UserStatus, presence: Option<Presence> }, // Sent to the audience from presence::presence_audience
     Profile(UserProfile),
     UserUpdated(User), // Broadcast when a user updates their profile
+    UsernameChanged { user_id: Uuid, old_username: String, new_username: String },
+    UsernameRejected(validation::FieldError),
+    ProfileUpdateRejected(Vec<validation::FieldIssue>), // Field-level errors for UpdateProfile
+    // Account lifecycle
+    AccountDeletionScheduled { delete_at: i64 },
+    AccountDeletionCancelled,
+    DataExport(AccountExport),
     Servers(Vec<Server>), // List of servers and their channels
+    // Discovery
+    DiscoveredServers { servers: Vec<ServerSummary>, has_more: bool, next_cursor: Option<PaginationCursor> },
+    ServerTagsUpdated { server_id: Uuid, tags: Vec<String> },
+    ServerIcons { icons: Vec<(Uuid, Option<String>)> }, // server_id, icon
+    // Server membership, broadcast to every remaining member
+    MemberJoined { server_id: Uuid, user: UserInfo },
+    MemberLeft { server_id: Uuid, user_id: Uuid },
+    ServerOwnershipTransferred { server_id: Uuid, previous_owner: Uuid, new_owner: Uuid },
+    ServerDeleted { server_id: Uuid },
+    MembershipRejected { server_id: Uuid, reason: membership::MembershipError },
     NewChannelMessage(ChannelMessage),
+    // Server roles
+    ServerRoles { server_id: Uuid, roles: Vec<ServerRole> },
+    ServerRoleUpdated(ServerRole), // Sent for both created and edited roles
+    ServerRoleDeleted { server_id: Uuid, role_id: Uuid },
+    MemberRolesUpdated { server_id: Uuid, user_id: Uuid, role_ids: Vec<Uuid> },
+    // Moderation
+    SanctionApplied(Sanction), // Sent to the affected user and the server's moderators
+    SanctionLifted { sanction_id: Uuid, user_id: Uuid, server_id: Uuid },
+    ServerSanctions { server_id: Uuid, sanctions: Vec<Sanction> },
+    // Friends
+    Friends { friends: Vec<UserInfo>, pending: Vec<Friendship> }, // Pending covers both incoming and outgoing requests
+    FriendRequestReceived { request: Friendship, from: UserInfo },
+    FriendRequestResponse { request_id: Uuid, accepted: bool, user: UserInfo },
+    FriendRemoved { user_id: Uuid },
+    // Blocking
+    BlockedUsers(Vec<UserInfo>),
+    UserBlocked { user_id: Uuid },
+    UserUnblocked { user_id: Uuid },
     // --- ENHANCED PAGINATION RESPONSES ---
     ChannelMessagesPaginated { 
         channel_id: Uuid, 
@@ -474,6 +1291,7 @@ pub fn create_initial_forums() -> Vec<Forum> {
         profile_pic: Some("system.png".to_string()),
         cover_banner: Some("system_banner.png".to_string()),
         status: UserStatus::Connected, // Default to connected
+        presence: None,
     };
     vec![
         Forum {
@@ -486,15 +1304,266 @@ pub fn create_initial_forums() -> Vec<Forum> {
                     title: "Militech's 'Aegis' Firewall - Any exploits?".to_string(),
                     author: system_user.clone(),
                     timestamp: 1633072800,
+                    edited_at: None,
+                    pinned: false,
+                    locked: false,
+                    tags: vec![],
+                    accepted_answer: None,
                     posts: vec![ Post {
                         id: Uuid::new_v4(),
                         author: system_user.clone(),
                         content: "I've been probing their new Aegis system. It's tough.".to_string(),
                         timestamp: 1633072800,
                         reply_to: None, // No parent post
+                        edited_at: None,
+                        score: 0,
                     }],
                 },
             ],
+            category_id: None,
+            position: 0,
+            tags: vec![],
         },
     ]
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{test_server, test_user};
+
+    const SECRET_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$c2FsdHNhbHQ$c2VjcmV0aGFzaA";
+
+    fn account() -> UserAccount {
+        UserAccount {
+            id: Uuid::new_v4(),
+            username: "alice".to_string(),
+            hash: SECRET_HASH.to_string(),
+            color: UserColor::new("Cyan"),
+            role: UserRole::User,
+            bio: Some("hi".to_string()),
+            url1: None,
+            url2: None,
+            url3: None,
+            location: None,
+            profile_pic: None,
+            cover_banner: None,
+            deletion_scheduled_at: None,
+        }
+    }
+
+    fn assert_no_credentials(message: &ServerMessage) {
+        let json = serde_json::to_string(message).unwrap();
+        assert!(!json.contains(SECRET_HASH), "hash leaked in {}", json);
+        assert!(!json.contains("\"hash\""), "hash field serialized in {}", json);
+    }
+
+    #[test]
+    fn profile_message_never_contains_hash() {
+        let account = account();
+        assert_no_credentials(&ServerMessage::Profile(account.profile()));
+        assert_no_credentials(&ServerMessage::Profile(account.into()));
+    }
+
+    // Spot checks of the serialized shape; the type-level guarantee is the
+    // `compile_fail` example on `UserAccount`
+    #[test]
+    fn user_carrying_messages_omit_credentials() {
+        let account = account();
+        let user = test_user(account.id);
+        let messages = vec![
+            ServerMessage::AuthSuccess(user.clone()),
+            ServerMessage::UserList(vec![user.clone()]),
+            ServerMessage::UserJoined(user.clone()),
+            ServerMessage::UserUpdated(user.clone()),
+            ServerMessage::DMUserList(vec![user.clone()]),
+            ServerMessage::MentionNotification { from: user.clone(), content: "hey".to_string() },
+        ];
+        for message in &messages {
+            assert_no_credentials(message);
+        }
+    }
+
+    #[test]
+    fn account_debug_redacts_hash() {
+        assert!(!format!("{:?}", account()).contains(SECRET_HASH));
+    }
+
+    fn server_with_roles(owner: Uuid) -> (Server, ServerRole, ServerRole) {
+        let mut server = test_server(owner, &[]);
+        let server_id = server.id;
+        let role = |name: &str, position, permissions| ServerRole {
+            id: Uuid::new_v4(),
+            server_id,
+            name: name.to_string(),
+            color: UserColor::new("Cyan"),
+            position,
+            permissions,
+        };
+        let admin = role("Admins", 10, ServerPermissions { manage_roles: true, kick_members: true, ..Default::default() });
+        let member = role("Members", 1, ServerPermissions::default());
+        server.roles = vec![admin.clone(), member.clone()];
+        (server, admin, member)
+    }
+
+    #[test]
+    fn roles_are_managed_only_below_own_highest_role() {
+        let owner = Uuid::new_v4();
+        let manager = Uuid::new_v4();
+        let (mut server, admin, member) = server_with_roles(owner);
+        server.role_assignments.insert(manager, vec![admin.id]);
+
+        assert!(server.can_manage_role(manager, &member));
+        assert!(!server.can_manage_role(manager, &admin));
+        assert!(server.can_manage_role(owner, &admin));
+        assert!(!server.can_manage_role(Uuid::new_v4(), &member));
+    }
+
+    #[test]
+    fn manage_roles_permission_is_required() {
+        let owner = Uuid::new_v4();
+        let user = Uuid::new_v4();
+        let (mut server, _, member) = server_with_roles(owner);
+        let mut high = member.clone();
+        high.id = Uuid::new_v4();
+        high.position = 20;
+        server.roles.push(high.clone());
+        server.role_assignments.insert(user, vec![high.id]);
+
+        assert!(!server.can_manage_role(user, &member));
+    }
+
+    #[test]
+    fn roles_from_another_server_are_rejected() {
+        let owner = Uuid::new_v4();
+        let (server, _, mut member) = server_with_roles(owner);
+        member.server_id = Uuid::new_v4();
+        assert!(!server.can_manage_role(owner, &member));
+    }
+
+    #[test]
+    fn role_cannot_be_raised_to_or_above_own_position() {
+        let owner = Uuid::new_v4();
+        let manager = Uuid::new_v4();
+        let (mut server, admin, member) = server_with_roles(owner);
+        server.role_assignments.insert(manager, vec![admin.id, member.id]);
+
+        assert!(server.can_update_role(manager, &member, Some(9), None));
+        assert!(!server.can_update_role(manager, &member, Some(10), None));
+        assert!(!server.can_update_role(manager, &member, Some(11), None));
+        assert!(server.can_update_role(owner, &member, Some(11), None));
+    }
+
+    #[test]
+    fn role_cannot_be_granted_permissions_the_actor_lacks() {
+        let owner = Uuid::new_v4();
+        let manager = Uuid::new_v4();
+        let (mut server, admin, member) = server_with_roles(owner);
+        server.role_assignments.insert(manager, vec![admin.id]);
+
+        let kick = ServerPermissions { kick_members: true, ..Default::default() };
+        assert!(server.can_update_role(manager, &member, None, Some(kick)));
+        assert!(!server.can_update_role(manager, &member, None, Some(ServerPermissions::all())));
+        assert!(!server.can_grant_role_settings(manager, 5, ServerPermissions::all()));
+        assert!(server.can_update_role(owner, &member, None, Some(ServerPermissions::all())));
+    }
+
+    #[test]
+    fn roles_are_assigned_only_to_outranked_members() {
+        let owner = Uuid::new_v4();
+        let (manager, peer, newcomer) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (mut server, admin, member) = server_with_roles(owner);
+        server.userlist.extend([manager, peer, newcomer]);
+        server.role_assignments.insert(manager, vec![admin.id]);
+        server.role_assignments.insert(peer, vec![admin.id, member.id]);
+
+        assert!(server.can_assign_role(manager, newcomer, &member));
+        // Same rank: can't strip or add roles on a peer
+        assert!(!server.can_assign_role(manager, peer, &member));
+        assert!(!server.can_assign_role(manager, owner, &member));
+        assert!(!server.can_assign_role(manager, Uuid::new_v4(), &member));
+        assert!(server.can_assign_role(owner, peer, &admin));
+        assert!(server.can_assign_role(owner, owner, &member));
+    }
+
+    #[test]
+    fn member_outranking_follows_highest_role() {
+        let owner = Uuid::new_v4();
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (mut server, admin, member) = server_with_roles(owner);
+        server.role_assignments.insert(a, vec![member.id, admin.id]);
+        server.role_assignments.insert(b, vec![member.id]);
+
+        assert!(server.outranks_member(a, b));
+        assert!(!server.outranks_member(b, a));
+        assert!(!server.outranks_member(b, b));
+        assert!(server.outranks_member(b, c));
+        assert!(!server.outranks_member(a, owner));
+        assert!(server.outranks_member(owner, a));
+    }
+
+    fn sanction(kind: SanctionKind, expires_at: Option<i64>) -> Sanction {
+        Sanction {
+            id: Uuid::new_v4(),
+            kind,
+            user_id: Uuid::new_v4(),
+            issued_by: Uuid::new_v4(),
+            server_id: Uuid::new_v4(),
+            channel_id: None,
+            reason: None,
+            created_at: 100,
+            expires_at,
+            lifted: false,
+        }
+    }
+
+    #[test]
+    fn sanctions_expire_at_the_boundary() {
+        let ban = sanction(SanctionKind::Ban, Some(200));
+        assert!(!ban.is_active_at(99));
+        assert!(ban.is_active_at(100));
+        assert!(ban.is_active_at(199));
+        assert!(!ban.is_active_at(200));
+        assert_eq!(ban.remaining_at(150), Some(50));
+        assert_eq!(ban.remaining_at(200), None);
+    }
+
+    #[test]
+    fn permanent_sanctions_stay_active_until_lifted() {
+        let mut ban = sanction(SanctionKind::Ban, None);
+        assert!(ban.is_active_at(i64::MAX));
+        assert_eq!(ban.remaining_at(1_000), None);
+        ban.lifted = true;
+        assert!(!ban.is_active_at(1_000));
+    }
+
+    #[test]
+    fn kicks_are_never_active() {
+        assert!(!sanction(SanctionKind::Kick, None).is_active_at(150));
+        assert!(!sanction(SanctionKind::Kick, Some(200)).is_active_at(150));
+    }
+
+    #[test]
+    fn sanction_lookup_respects_kind_server_and_channel() {
+        let channel = Uuid::new_v4();
+        let mut mute = sanction(SanctionKind::Mute, Some(200));
+        mute.channel_id = Some(channel);
+        let timeout = Sanction {
+            user_id: mute.user_id,
+            server_id: mute.server_id,
+            ..sanction(SanctionKind::Timeout, Some(300))
+        };
+        let sanctions = vec![mute.clone(), timeout.clone()];
+        let (user, server) = (mute.user_id, mute.server_id);
+
+        assert!(is_sanctioned(&sanctions, user, SanctionKind::Mute, server, Some(channel), 150));
+        assert!(!is_sanctioned(&sanctions, user, SanctionKind::Mute, server, Some(Uuid::new_v4()), 150));
+        assert!(!is_sanctioned(&sanctions, user, SanctionKind::Mute, server, Some(channel), 250));
+        // Timeouts cover every channel of the server
+        let found = active_sanction(&sanctions, user, SanctionKind::Timeout, server, Some(channel), 250);
+        assert_eq!(found.map(|s| s.id), Some(timeout.id));
+        assert!(!is_sanctioned(&sanctions, user, SanctionKind::Ban, server, None, 150));
+        assert!(!is_sanctioned(&sanctions, user, SanctionKind::Timeout, Uuid::new_v4(), None, 150));
+        assert!(!is_sanctioned(&sanctions, Uuid::new_v4(), SanctionKind::Timeout, server, None, 150));
+    }
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Server;
+
+/// Why a leave/transfer/delete request was refused
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MembershipError {
+    NotMember,
+    NotOwner,
+    OwnerCannotLeave, // Transfer ownership or delete the server first
+    TargetNotMember,
+    AlreadyOwner,
+    ConfirmationMismatch,
+}
+
+impl std::fmt::Display for MembershipError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MembershipError::NotMember => write!(f, "You are not a member of this server"),
+            MembershipError::NotOwner => write!(f, "Only the server owner can do this"),
+            MembershipError::OwnerCannotLeave => {
+                write!(f, "The owner can't leave; transfer ownership or delete the server first")
+            }
+            MembershipError::TargetNotMember => write!(f, "The new owner must be a member of the server"),
+            MembershipError::AlreadyOwner => write!(f, "That user already owns the server"),
+            MembershipError::ConfirmationMismatch => write!(f, "Type the server name exactly to confirm"),
+        }
+    }
+}
+
+impl Server {
+    pub fn is_member(&self, user_id: Uuid) -> bool {
+        self.userlist.contains(&user_id)
+    }
+
+    pub fn check_leave(&self, user_id: Uuid) -> Result<(), MembershipError> {
+        if !self.is_member(user_id) {
+            return Err(MembershipError::NotMember);
+        }
+        if user_id == self.owner {
+            return Err(MembershipError::OwnerCannotLeave);
+        }
+        Ok(())
+    }
+
+    /// Owner-only; `confirm_name` must match the server name exactly
+    pub fn check_transfer_ownership(
+        &self,
+        actor: Uuid,
+        new_owner: Uuid,
+        confirm_name: &str,
+    ) -> Result<(), MembershipError> {
+        if actor != self.owner {
+            return Err(MembershipError::NotOwner);
+        }
+        if new_owner == self.owner {
+            return Err(MembershipError::AlreadyOwner);
+        }
+        if !self.is_member(new_owner) {
+            return Err(MembershipError::TargetNotMember);
+        }
+        if confirm_name != self.name {
+            return Err(MembershipError::ConfirmationMismatch);
+        }
+        Ok(())
+    }
+
+    /// Owner-only; `confirm_name` must match the server name exactly
+    pub fn check_delete(&self, actor: Uuid, confirm_name: &str) -> Result<(), MembershipError> {
+        if actor != self.owner {
+            return Err(MembershipError::NotOwner);
+        }
+        if confirm_name != self.name {
+            return Err(MembershipError::ConfirmationMismatch);
+        }
+        Ok(())
+    }
+
+    /// Remove a member everywhere they are referenced: member list, mods, roles and channels
+    pub fn remove_member(&mut self, user_id: Uuid) {
+        self.userlist.retain(|id| *id != user_id);
+        self.mods.retain(|id| *id != user_id);
+        self.role_assignments.remove(&user_id);
+        for channel in &mut self.channels {
+            channel.userlist.retain(|id| *id != user_id);
+            channel.permissions.can_read.retain(|id| *id != user_id);
+            channel.permissions.can_write.retain(|id| *id != user_id);
+        }
+    }
+
+    /// Hand the server to `new_owner`. The previous owner stays on as a moderator.
+    /// Call `check_transfer_ownership` first.
+    pub fn transfer_ownership(&mut self, new_owner: Uuid) -> Uuid {
+        let previous = std::mem::replace(&mut self.owner, new_owner);
+        self.mods.retain(|id| *id != new_owner);
+        if !self.mods.contains(&previous) {
+            self.mods.push(previous);
+        }
+        previous
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_server;
+    use crate::{Channel, ChannelPermissions};
+
+    #[test]
+    fn owner_cannot_leave() {
+        let (owner, member) = (Uuid::new_v4(), Uuid::new_v4());
+        let server = test_server(owner, &[member]);
+        assert_eq!(server.check_leave(owner), Err(MembershipError::OwnerCannotLeave));
+        assert_eq!(server.check_leave(member), Ok(()));
+        assert_eq!(server.check_leave(Uuid::new_v4()), Err(MembershipError::NotMember));
+    }
+
+    #[test]
+    fn transfer_checks() {
+        let (owner, member) = (Uuid::new_v4(), Uuid::new_v4());
+        let server = test_server(owner, &[member]);
+        let check = |actor, target, name| server.check_transfer_ownership(actor, target, name);
+        assert_eq!(check(member, owner, "Test"), Err(MembershipError::NotOwner));
+        assert_eq!(check(owner, owner, "Test"), Err(MembershipError::AlreadyOwner));
+        assert_eq!(check(owner, Uuid::new_v4(), "Test"), Err(MembershipError::TargetNotMember));
+        assert_eq!(check(owner, member, "test"), Err(MembershipError::ConfirmationMismatch));
+        assert_eq!(check(owner, member, "Test"), Ok(()));
+    }
+
+    #[test]
+    fn removed_members_lose_roles_and_channel_access() {
+        let (owner, member) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut server = test_server(owner, &[member]);
+        server.mods.push(member);
+        server.role_assignments.insert(member, vec![Uuid::new_v4()]);
+        server.channels.push(Channel {
+            id: Uuid::new_v4(),
+            server_id: server.id,
+            name: "general".into(),
+            description: String::new(),
+            permissions: ChannelPermissions { can_read: vec![owner, member], can_write: vec![member] },
+            userlist: vec![owner, member],
+            messages: vec![],
+        });
+
+        server.remove_member(member);
+        assert!(!server.is_member(member));
+        assert!(server.mods.is_empty());
+        assert!(!server.role_assignments.contains_key(&member));
+        let channel = &server.channels[0];
+        assert_eq!(channel.userlist, vec![owner]);
+        assert_eq!(channel.permissions.can_read, vec![owner]);
+        assert!(channel.permissions.can_write.is_empty());
+    }
+
+    #[test]
+    fn previous_owner_becomes_a_moderator() {
+        let (owner, member) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut server = test_server(owner, &[member]);
+        server.mods.push(member);
+
+        assert_eq!(server.transfer_ownership(member), owner);
+        assert_eq!(server.owner, member);
+        assert_eq!(server.mods, vec![owner]);
+        assert!(server.is_member(owner));
+    }
+}
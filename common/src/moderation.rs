@@ -0,0 +1,417 @@
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::config::ModerationConfig;
+
+/// Outcome of running a message through the auto-moderator.
+/// The server applies it the same way for every message type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModerationVerdict {
+    /// Deliver the message unchanged
+    Allow,
+    /// Deliver `content`, which has blocked words masked out
+    Redact { content: String, strikes: usize },
+    /// The user reached `warning_threshold`: deliver `content` if present
+    /// (None means the message itself must be dropped) and warn the user
+    Warn { content: Option<String>, strikes: usize },
+    /// Drop the message and tell the sender why
+    Reject { reason: RejectReason },
+    /// The user reached `auto_ban_threshold`: drop the message and ban them
+    AutoBan { strikes: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectReason {
+    TooLong { length: usize, limit: usize },
+    BlockedPattern(String),
+}
+
+impl std::fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RejectReason::TooLong { length, limit } => {
+                write!(f, "Message is too long ({} characters, limit is {})", length, limit)
+            }
+            RejectReason::BlockedPattern(_) => write!(f, "Message contains blocked content"),
+        }
+    }
+}
+
+/// Auto-moderation engine built from `ModerationConfig`.
+///
+/// Word lists and patterns are compiled once in `new`; strikes are tracked per user
+/// for the lifetime of the moderator (reset them with `reset_strikes`).
+#[derive(Debug)]
+pub struct Moderator {
+    enabled: bool,
+    words: Option<Regex>,
+    patterns: Vec<(String, Regex)>,
+    warning_threshold: usize,
+    auto_ban_threshold: usize,
+    message_length_limit: usize,
+    strikes: HashMap<Uuid, usize>,
+}
+
+impl Moderator {
+    pub fn new(config: &ModerationConfig) -> Result<Self, regex::Error> {
+        let words: Vec<String> = config
+            .blocked_words
+            .iter()
+            .map(|w| normalize(w).text)
+            .filter(|w| !w.trim().is_empty())
+            .map(|w| word_pattern(w.trim()))
+            .collect();
+        let words = if words.is_empty() { None } else { Some(Regex::new(&words.join("|"))?) };
+
+        let patterns = config
+            .blocked_patterns
+            .iter()
+            .map(|p| {
+                RegexBuilder::new(p)
+                    .case_insensitive(true)
+                    .build()
+                    .map(|re| (p.clone(), re))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            enabled: config.auto_moderation_enabled,
+            words,
+            patterns,
+            warning_threshold: config.warning_threshold,
+            auto_ban_threshold: config.auto_ban_threshold,
+            message_length_limit: config.message_length_limit,
+            strikes: HashMap::new(),
+        })
+    }
+
+    /// Check a message from `user_id`, recording a strike if it breaks a rule
+    pub fn check(&mut self, user_id: Uuid, content: &str) -> ModerationVerdict {
+        let length = content.chars().count();
+        if length > self.message_length_limit {
+            return ModerationVerdict::Reject {
+                reason: RejectReason::TooLong { length, limit: self.message_length_limit },
+            };
+        }
+        if !self.enabled {
+            return ModerationVerdict::Allow;
+        }
+
+        let normalized = normalize(content);
+
+        // Patterns are checked against both the raw and the normalized text so that
+        // authored regexes with digits or punctuation still work as written.
+        if let Some((pattern, _)) = self
+            .patterns
+            .iter()
+            .find(|(_, re)| re.is_match(content) || re.is_match(&normalized.text))
+        {
+            let reason = RejectReason::BlockedPattern(pattern.clone());
+            return match self.add_strike(user_id) {
+                (strikes, Escalation::Ban) => ModerationVerdict::AutoBan { strikes },
+                (strikes, Escalation::Warn) => ModerationVerdict::Warn { content: None, strikes },
+                (_, Escalation::None) => ModerationVerdict::Reject { reason },
+            };
+        }
+
+        let Some(redacted) = self.redact(content, &normalized) else {
+            return ModerationVerdict::Allow;
+        };
+        match self.add_strike(user_id) {
+            (strikes, Escalation::Ban) => ModerationVerdict::AutoBan { strikes },
+            (strikes, Escalation::Warn) => ModerationVerdict::Warn { content: Some(redacted), strikes },
+            (strikes, Escalation::None) => ModerationVerdict::Redact { content: redacted, strikes },
+        }
+    }
+
+    /// Whether the content would trip any rule, without recording a strike
+    pub fn is_clean(&self, content: &str) -> bool {
+        if content.chars().count() > self.message_length_limit {
+            return false;
+        }
+        if !self.enabled {
+            return true;
+        }
+        let normalized = normalize(content);
+        !self.patterns.iter().any(|(_, re)| re.is_match(content) || re.is_match(&normalized.text))
+            && self.words.as_ref().is_none_or(|re| !re.is_match(&normalized.text))
+    }
+
+    pub fn strikes(&self, user_id: Uuid) -> usize {
+        self.strikes.get(&user_id).copied().unwrap_or(0)
+    }
+
+    pub fn reset_strikes(&mut self, user_id: Uuid) {
+        self.strikes.remove(&user_id);
+    }
+
+    /// Record a strike and report which threshold, if any, it reached
+    fn add_strike(&mut self, user_id: Uuid) -> (usize, Escalation) {
+        let strikes = self.strikes.entry(user_id).or_insert(0);
+        *strikes += 1;
+        let strikes = *strikes;
+        let escalation = if self.auto_ban_threshold > 0 && strikes >= self.auto_ban_threshold {
+            Escalation::Ban
+        } else if self.warning_threshold > 0 && strikes >= self.warning_threshold {
+            Escalation::Warn
+        } else {
+            Escalation::None
+        };
+        (strikes, escalation)
+    }
+
+    /// Mask every blocked word in the original text, or None if nothing matched
+    fn redact(&self, original: &str, normalized: &Normalized) -> Option<String> {
+        let re = self.words.as_ref()?;
+        let mut ranges = Vec::new();
+        for m in re.find_iter(&normalized.text) {
+            if let Some(range) = normalized.original_range(m.start(), m.end()) {
+                ranges.push(range);
+            }
+        }
+        if ranges.is_empty() {
+            return None;
+        }
+
+        let mut out = String::with_capacity(original.len());
+        let mut last = 0;
+        for (start, end) in ranges {
+            out.push_str(&original[last..start]);
+            out.extend(std::iter::repeat_n('*', original[start..end].chars().count()));
+            last = end;
+        }
+        out.push_str(&original[last..]);
+        Some(out)
+    }
+}
+
+/// Match a blocked word as a whole word. `\b` is only added on sides that are
+/// word characters, otherwise entries such as "c++" could never match.
+fn word_pattern(word: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let start = if word.starts_with(is_word) { r"\b" } else { "" };
+    let end = if word.ends_with(is_word) { r"\b" } else { "" };
+    format!("{}{}{}", start, regex::escape(word), end)
+}
+
+enum Escalation {
+    None,
+    Warn,
+    Ban,
+}
+
+/// Text folded for matching, with a map back to the original byte offsets
+struct Normalized {
+    text: String,
+    spans: Vec<(usize, usize, usize)>, // (normalized offset, original start, original end)
+}
+
+impl Normalized {
+    fn original_range(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        let first = self.spans.binary_search_by_key(&start, |s| s.0).ok()?;
+        let last = self.spans.iter().rposition(|s| s.0 < end)?;
+        Some((self.spans[first].1, self.spans[last].2))
+    }
+}
+
+/// Lowercase, undo common leetspeak and map Unicode look-alikes to ASCII.
+/// Zero-width and combining characters are dropped.
+fn normalize(input: &str) -> Normalized {
+    let folded: Vec<(usize, usize, char)> = input
+        .char_indices()
+        .filter_map(|(start, c)| fold_char(c).map(|f| (start, start + c.len_utf8(), f)))
+        .collect();
+
+    let mut text = String::with_capacity(input.len());
+    let mut spans = Vec::with_capacity(folded.len());
+    for (i, &(start, end, c)) in folded.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|p| folded[p].2);
+        let next = folded.get(i + 1).map(|n| n.2);
+        spans.push((text.len(), start, end));
+        text.push(unleet(c, prev, next));
+    }
+    Normalized { text, spans }
+}
+
+/// Leetspeak substitutions only count inside words, so "2000" and a trailing "!"
+/// are left alone while "h3ll0" and "$hit" are folded.
+fn unleet(c: char, prev: Option<char>, next: Option<char>) -> char {
+    let letter_next = next.is_some_and(|n| n.is_alphabetic());
+    let letter_near = letter_next || prev.is_some_and(|p| p.is_alphabetic());
+    match c {
+        '!' | '|' if letter_next => 'i',
+        '@' if letter_next => 'a',
+        '$' if letter_next => 's',
+        '0' if letter_near => 'o',
+        '1' if letter_near => 'i',
+        '3' if letter_near => 'e',
+        '4' if letter_near => 'a',
+        '5' if letter_near => 's',
+        '7' if letter_near => 't',
+        '8' if letter_near => 'b',
+        other => other,
+    }
+}
+
+/// Fold one character to its plain lowercase Latin look-alike, or None for invisible characters.
+/// Shared with `validation` for username confusable checks.
+pub(crate) fn fold_char(c: char) -> Option<char> {
+    // Zero-width characters and combining diacritics are used to split words invisibly
+    if matches!(c, '\u{200B}'..='\u{200F}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}' | '\u{0300}'..='\u{036F}') {
+        return None;
+    }
+    // Fullwidth ASCII (e.g. ｂａｄ)
+    let c = match c {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        _ => c,
+    };
+    let c = c.to_lowercase().next().unwrap_or(c);
+    Some(match c {
+        // Cyrillic and Greek confusables
+        'а' | 'α' => 'a',
+        'в' | 'β' => 'b',
+        'с' | 'ϲ' => 'c',
+        'е' | 'ε' | 'ё' => 'e',
+        'һ' => 'h',
+        'і' | 'ї' | 'ι' => 'i',
+        'ј' => 'j',
+        'к' | 'κ' => 'k',
+        'м' => 'm',
+        'н' | 'η' => 'n',
+        'о' | 'ο' | 'σ' => 'o',
+        'р' | 'ρ' => 'p',
+        'ѕ' => 's',
+        'т' | 'τ' => 't',
+        'υ' => 'u',
+        'ν' => 'v',
+        'ш' | 'ω' => 'w',
+        'х' | 'χ' => 'x',
+        'у' | 'γ' => 'y',
+        // Accented Latin
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' => 'c',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        other => other,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(words: &[&str]) -> ModerationConfig {
+        ModerationConfig {
+            auto_moderation_enabled: true,
+            blocked_words: words.iter().map(|w| w.to_string()).collect(),
+            blocked_patterns: vec![],
+            auto_ban_threshold: 0,
+            warning_threshold: 0,
+            message_length_limit: 500,
+            channel_creation_role: "User".to_string(),
+        }
+    }
+
+    fn redacted(moderator: &mut Moderator, content: &str) -> Option<String> {
+        match moderator.check(Uuid::new_v4(), content) {
+            ModerationVerdict::Redact { content, .. } => Some(content),
+            ModerationVerdict::Allow => None,
+            other => panic!("unexpected verdict {:?}", other),
+        }
+    }
+
+    #[test]
+    fn leetspeak_is_folded_inside_words_only() {
+        let mut moderator = Moderator::new(&config(&["hello"])).unwrap();
+        assert_eq!(redacted(&mut moderator, "say h3ll0 now").as_deref(), Some("say ***** now"));
+        assert_eq!(redacted(&mut moderator, "in 2000!"), None);
+
+        let mut moderator = Moderator::new(&config(&["shit"])).unwrap();
+        assert_eq!(redacted(&mut moderator, "$hit!").as_deref(), Some("****!"));
+    }
+
+    #[test]
+    fn confusables_and_fullwidth_are_folded() {
+        let mut moderator = Moderator::new(&config(&["badword"])).unwrap();
+        assert_eq!(redacted(&mut moderator, "ＢＡＤＷＯＲＤ!").as_deref(), Some("*******!"));
+        // Cyrillic а and о
+        assert_eq!(redacted(&mut moderator, "bаdwоrd").as_deref(), Some("*******"));
+        assert_eq!(redacted(&mut moderator, "bádword").as_deref(), Some("*******"));
+    }
+
+    #[test]
+    fn zero_width_characters_do_not_split_words() {
+        let mut moderator = Moderator::new(&config(&["spam"])).unwrap();
+        let content = "no s\u{200B}p\u{200D}am here";
+        assert_eq!(redacted(&mut moderator, content).as_deref(), Some("no ****** here"));
+    }
+
+    #[test]
+    fn redaction_maps_back_to_original_offsets() {
+        let mut moderator = Moderator::new(&config(&["bad"])).unwrap();
+        // Multi-byte look-alikes before and inside the match must not shift the mask
+        assert_eq!(redacted(&mut moderator, "ｂａｄ and bad, é bad").as_deref(), Some("*** and ***, é ***"));
+        assert_eq!(redacted(&mut moderator, "badger is fine"), None);
+    }
+
+    #[test]
+    fn words_with_non_word_edges_match() {
+        let mut moderator = Moderator::new(&config(&["c++", "#tag"])).unwrap();
+        assert_eq!(redacted(&mut moderator, "I like c++ a lot").as_deref(), Some("I like *** a lot"));
+        assert_eq!(redacted(&mut moderator, "see #tag").as_deref(), Some("see ****"));
+        assert_eq!(redacted(&mut moderator, "abc++"), None);
+    }
+
+    #[test]
+    fn strikes_escalate_to_warning_then_ban() {
+        let mut config = config(&["bad"]);
+        config.warning_threshold = 2;
+        config.auto_ban_threshold = 3;
+        let mut moderator = Moderator::new(&config).unwrap();
+        let user = Uuid::new_v4();
+
+        assert_eq!(moderator.check(user, "bad"), ModerationVerdict::Redact { content: "***".to_string(), strikes: 1 });
+        assert_eq!(
+            moderator.check(user, "bad"),
+            ModerationVerdict::Warn { content: Some("***".to_string()), strikes: 2 }
+        );
+        assert_eq!(moderator.check(user, "bad"), ModerationVerdict::AutoBan { strikes: 3 });
+        assert_eq!(moderator.check(user, "fine"), ModerationVerdict::Allow);
+
+        moderator.reset_strikes(user);
+        assert_eq!(moderator.strikes(user), 0);
+    }
+
+    #[test]
+    fn blocked_patterns_reject_and_count_strikes() {
+        let mut config = config(&[]);
+        config.blocked_patterns = vec![r"discord\.gg/\w+".to_string()];
+        config.warning_threshold = 2;
+        let mut moderator = Moderator::new(&config).unwrap();
+        let user = Uuid::new_v4();
+
+        assert!(matches!(
+            moderator.check(user, "join DISCORD.GG/abc"),
+            ModerationVerdict::Reject { reason: RejectReason::BlockedPattern(_) }
+        ));
+        assert_eq!(moderator.check(user, "discord.gg/x"), ModerationVerdict::Warn { content: None, strikes: 2 });
+    }
+
+    #[test]
+    fn overlong_messages_are_rejected_without_strikes() {
+        let mut config = config(&[]);
+        config.message_length_limit = 5;
+        let mut moderator = Moderator::new(&config).unwrap();
+        let user = Uuid::new_v4();
+        assert_eq!(
+            moderator.check(user, "too long"),
+            ModerationVerdict::Reject { reason: RejectReason::TooLong { length: 8, limit: 5 } }
+        );
+        assert_eq!(moderator.strikes(user), 0);
+    }
+}
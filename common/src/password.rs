@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::SecurityConfig;
+
+/// Bundled list of passwords that are always rejected when secure passwords are required
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+/// Minimum number of distinct character classes a secure password must use
+const REQUIRED_CHAR_CLASSES: usize = 3;
+
+/// Longest run of one repeated character a secure password may contain
+const MAX_REPEATED_RUN: usize = 2;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+}
+
+/// A single failed password rule, structured so the client can render it inline
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum PasswordIssue {
+    TooShort { min: usize, actual: usize },
+    TooFewCharClasses { required: usize, missing: Vec<CharClass> },
+    ContainsUsername,
+    CommonPassword,
+    RepeatedCharacters { character: char, run: usize },
+}
+
+impl std::fmt::Display for PasswordIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasswordIssue::TooShort { min, actual } => {
+                write!(f, "Password must be at least {} characters (currently {})", min, actual)
+            }
+            PasswordIssue::TooFewCharClasses { required, missing } => {
+                let missing: Vec<&str> = missing
+                    .iter()
+                    .map(|c| match c {
+                        CharClass::Lowercase => "lowercase letters",
+                        CharClass::Uppercase => "uppercase letters",
+                        CharClass::Digit => "digits",
+                        CharClass::Symbol => "symbols",
+                    })
+                    .collect();
+                write!(f, "Password must mix at least {} of: lowercase, uppercase, digits, symbols (missing {})", required, missing.join(", "))
+            }
+            PasswordIssue::ContainsUsername => write!(f, "Password must not contain your username"),
+            PasswordIssue::CommonPassword => write!(f, "Password is too common"),
+            PasswordIssue::RepeatedCharacters { character, run } => {
+                write!(f, "Password repeats '{}' {} times in a row", character, run)
+            }
+        }
+    }
+}
+
+/// Password rules shared by the client (before submitting `Register`/`UpdatePassword`)
+/// and the server (before hashing).
+///
+/// The length rule always applies; every other rule only applies when
+/// `require_secure_passwords` is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_secure: bool,
+}
+
+impl PasswordPolicy {
+    pub fn from_config(config: &SecurityConfig) -> Self {
+        Self {
+            min_length: config.min_password_length,
+            require_secure: config.require_secure_passwords,
+        }
+    }
+
+    /// Check a password, reporting every rule it fails rather than only the first
+    pub fn validate(&self, password: &str, username: Option<&str>) -> Result<(), Vec<PasswordIssue>> {
+        let mut issues = Vec::new();
+
+        let length = password.chars().count();
+        if length < self.min_length {
+            issues.push(PasswordIssue::TooShort { min: self.min_length, actual: length });
+        }
+
+        if self.require_secure {
+            let missing: Vec<CharClass> = [CharClass::Lowercase, CharClass::Uppercase, CharClass::Digit, CharClass::Symbol]
+                .into_iter()
+                .filter(|class| !password.chars().any(|c| char_class(c) == *class))
+                .collect();
+            if 4 - missing.len() < REQUIRED_CHAR_CLASSES {
+                issues.push(PasswordIssue::TooFewCharClasses { required: REQUIRED_CHAR_CLASSES, missing });
+            }
+
+            let lowered = password.to_lowercase();
+            if let Some(username) = username.map(|u| u.trim().to_lowercase()) {
+                if username.chars().count() >= 3 && lowered.contains(&username) {
+                    issues.push(PasswordIssue::ContainsUsername);
+                }
+            }
+
+            if is_common_password(&lowered) {
+                issues.push(PasswordIssue::CommonPassword);
+            }
+
+            if let Some((character, run)) = longest_run(password).filter(|(_, run)| *run > MAX_REPEATED_RUN) {
+                issues.push(PasswordIssue::RepeatedCharacters { character, run });
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_lowercase() {
+        CharClass::Lowercase
+    } else if c.is_uppercase() {
+        CharClass::Uppercase
+    } else if c.is_numeric() {
+        CharClass::Digit
+    } else {
+        CharClass::Symbol
+    }
+}
+
+fn is_common_password(lowered: &str) -> bool {
+    COMMON_PASSWORDS.lines().any(|p| p == lowered)
+}
+
+/// The character with the longest consecutive run and the run length
+fn longest_run(password: &str) -> Option<(char, usize)> {
+    let mut best: Option<(char, usize)> = None;
+    let mut prev = None;
+    let mut run = 0;
+    for c in password.chars() {
+        if prev == Some(c) {
+            run += 1;
+        } else {
+            prev = Some(c);
+            run = 1;
+        }
+        if best.is_none_or(|(_, longest)| run > longest) {
+            best = Some((c, run));
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECURE: PasswordPolicy = PasswordPolicy { min_length: 8, require_secure: true };
+
+    fn issues(policy: PasswordPolicy, password: &str, username: Option<&str>) -> Vec<PasswordIssue> {
+        policy.validate(password, username).err().unwrap_or_default()
+    }
+
+    #[test]
+    fn strong_password_passes() {
+        assert_eq!(SECURE.validate("Tr0ub4dor&horse", Some("alice")), Ok(()));
+    }
+
+    #[test]
+    fn too_short() {
+        assert_eq!(issues(SECURE, "Ab1!x", None), vec![PasswordIssue::TooShort { min: 8, actual: 5 }]);
+    }
+
+    #[test]
+    fn too_few_char_classes() {
+        assert_eq!(
+            issues(SECURE, "onlylowercase12", None),
+            vec![PasswordIssue::TooFewCharClasses {
+                required: 3,
+                missing: vec![CharClass::Uppercase, CharClass::Symbol],
+            }]
+        );
+    }
+
+    #[test]
+    fn contains_username_case_insensitively() {
+        assert_eq!(issues(SECURE, "xX-Alice-2024", Some("alice")), vec![PasswordIssue::ContainsUsername]);
+        // Very short usernames would match too many passwords to be useful
+        assert_eq!(SECURE.validate("xX-Al-2024!", Some("al")), Ok(()));
+    }
+
+    #[test]
+    fn common_password() {
+        assert_eq!(issues(SECURE, "Password1", None), vec![PasswordIssue::CommonPassword]);
+    }
+
+    #[test]
+    fn repeated_characters() {
+        assert_eq!(
+            issues(SECURE, "Gooood-Day42", None),
+            vec![PasswordIssue::RepeatedCharacters { character: 'o', run: 4 }]
+        );
+    }
+
+    #[test]
+    fn every_failed_rule_is_reported() {
+        assert_eq!(
+            issues(SECURE, "bobbb", Some("bob")),
+            vec![
+                PasswordIssue::TooShort { min: 8, actual: 5 },
+                PasswordIssue::TooFewCharClasses {
+                    required: 3,
+                    missing: vec![CharClass::Uppercase, CharClass::Digit, CharClass::Symbol],
+                },
+                PasswordIssue::ContainsUsername,
+                PasswordIssue::RepeatedCharacters { character: 'b', run: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_length_applies_when_secure_passwords_are_off() {
+        let policy = PasswordPolicy { min_length: 6, require_secure: false };
+        assert_eq!(policy.validate("password", Some("password")), Ok(()));
+        assert_eq!(policy.validate("aaaaaa", None), Ok(()));
+        assert_eq!(issues(policy, "abc", None), vec![PasswordIssue::TooShort { min: 6, actual: 3 }]);
+    }
+}
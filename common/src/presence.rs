@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::config::PresenceConfig;
+use crate::{PresenceVisibility, Server, UserStatus};
+
+/// Tracks keyboard/network activity on the client and decides when to
+/// switch to `Away` and back. Manual statuses (Busy, a manual Away) are never overridden.
+#[derive(Debug, Clone)]
+pub struct IdleTracker {
+    idle_after: Option<i64>, // Seconds, None when auto-away is disabled
+    last_activity: i64,
+    auto_away: bool, // True when the current Away status was set by this tracker
+}
+
+impl IdleTracker {
+    pub fn new(config: &PresenceConfig, now: i64) -> Self {
+        Self {
+            idle_after: config.auto_away_enabled.then_some(config.auto_away_minutes as i64 * 60),
+            last_activity: now,
+            auto_away: false,
+        }
+    }
+
+    /// Call on user input. Returns `Connected` if the user is still in the Away
+    /// status this tracker set; a status they picked since then is left alone.
+    pub fn record_activity(&mut self, now: i64, current: UserStatus) -> Option<UserStatus> {
+        self.last_activity = now;
+        let was_auto_away = std::mem::take(&mut self.auto_away);
+        (was_auto_away && current == UserStatus::Away).then_some(UserStatus::Connected)
+    }
+
+    /// Call periodically. Returns `Away` once the idle time is exceeded while `Connected`.
+    pub fn tick(&mut self, now: i64, current: UserStatus) -> Option<UserStatus> {
+        let idle_after = self.idle_after?;
+        if current == UserStatus::Connected && !self.auto_away && now - self.last_activity >= idle_after {
+            self.auto_away = true;
+            return Some(UserStatus::Away);
+        }
+        None
+    }
+
+    pub fn is_auto_away(&self) -> bool {
+        self.auto_away
+    }
+}
+
+/// Users who should receive `PresenceUpdated` for `user_id`: friends, plus members of
+/// any server the user belongs to when visibility is `Everyone`. Friends are included
+/// even if they share no server. The user themselves is never included.
+pub fn presence_audience(
+    user_id: Uuid,
+    servers: &[Server],
+    visibility: PresenceVisibility,
+    friends: &HashSet<Uuid>,
+) -> HashSet<Uuid> {
+    let co_members = servers
+        .iter()
+        .filter(|s| visibility == PresenceVisibility::Everyone && s.userlist.contains(&user_id))
+        .flat_map(|s| s.userlist.iter().copied());
+    let friends = friends.iter().copied().filter(|_| visibility != PresenceVisibility::Nobody);
+    co_members.chain(friends).filter(|id| *id != user_id).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_server;
+
+    fn tracker() -> IdleTracker {
+        IdleTracker::new(&PresenceConfig { auto_away_enabled: true, auto_away_minutes: 1 }, 0)
+    }
+
+    #[test]
+    fn goes_away_when_idle_and_back_on_activity() {
+        let mut idle = tracker();
+        assert_eq!(idle.tick(59, UserStatus::Connected), None);
+        assert_eq!(idle.tick(60, UserStatus::Connected), Some(UserStatus::Away));
+        assert!(idle.is_auto_away());
+        assert_eq!(idle.tick(120, UserStatus::Away), None);
+        assert_eq!(idle.record_activity(130, UserStatus::Away), Some(UserStatus::Connected));
+        assert!(!idle.is_auto_away());
+        assert_eq!(idle.tick(189, UserStatus::Connected), None);
+    }
+
+    #[test]
+    fn never_overrides_manual_statuses() {
+        let mut idle = tracker();
+        assert_eq!(idle.tick(600, UserStatus::Busy), None);
+        assert_eq!(idle.record_activity(601, UserStatus::Away), None); // Away set by the user
+
+        // Switched to Busy by hand while auto-away
+        assert_eq!(idle.tick(700, UserStatus::Connected), Some(UserStatus::Away));
+        assert_eq!(idle.record_activity(710, UserStatus::Busy), None);
+        assert!(!idle.is_auto_away());
+    }
+
+    #[test]
+    fn disabled_tracker_never_goes_away() {
+        let mut idle = IdleTracker::new(&PresenceConfig { auto_away_enabled: false, auto_away_minutes: 1 }, 0);
+        assert_eq!(idle.tick(10_000, UserStatus::Connected), None);
+    }
+
+    #[test]
+    fn audience_depends_on_visibility() {
+        let (me, member, friend, stranger) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let servers = vec![test_server(me, &[member]), test_server(friend, &[stranger])];
+        let friends = HashSet::from([friend]);
+
+        let everyone = presence_audience(me, &servers, PresenceVisibility::Everyone, &friends);
+        assert_eq!(everyone, HashSet::from([member, friend]));
+        let friends_only = presence_audience(me, &servers, PresenceVisibility::FriendsOnly, &friends);
+        assert_eq!(friends_only, HashSet::from([friend]));
+        assert!(presence_audience(me, &servers, PresenceVisibility::Nobody, &friends).is_empty());
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::config::RateLimitConfig;
+use crate::ClientMessage;
+
+/// The six limits defined in `RateLimitConfig`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitCategory {
+    Messages,
+    Requests,
+    FileUploads,
+    Registration,
+    Login,
+    ChannelJoins,
+}
+
+/// Who a limit applies to. Use `Ip` for unauthenticated requests such as login and registration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    User(Uuid),
+    Ip(IpAddr),
+}
+
+impl ClientMessage {
+    /// Rate limit bucket this message counts against. The match is exhaustive on
+    /// purpose: adding a variant forces a decision about its bucket.
+    pub fn rate_limit_category(&self) -> RateLimitCategory {
+        match self {
+            ClientMessage::Register { .. } => RateLimitCategory::Registration,
+            // Anything that checks a password or a one-time code shares the login budget,
+            // so it can't be used to brute-force credentials at the general request rate
+            ClientMessage::Login { .. }
+            | ClientMessage::SubmitTotp { .. }
+            | ClientMessage::SubmitRecoveryCode { .. }
+            | ClientMessage::ConfirmTotpEnrollment { .. }
+            | ClientMessage::DisableTotp { .. }
+            | ClientMessage::ChangeUsername { .. }
+            | ClientMessage::DeleteAccount { .. } => RateLimitCategory::Login,
+            // Anything that creates content or reaches other users
+            ClientMessage::SendDirectMessage { .. }
+            | ClientMessage::SendChannelMessage { .. }
+            | ClientMessage::CreateThread { .. }
+            | ClientMessage::CreatePost { .. }
+            | ClientMessage::CreatePostReply { .. }
+            | ClientMessage::EditPost { .. }
+            | ClientMessage::EditThreadTitle { .. }
+            | ClientMessage::SendServerInvite { .. }
+            | ClientMessage::CreateInviteLink { .. }
+            | ClientMessage::SendFriendRequest { .. } => RateLimitCategory::Messages,
+            ClientMessage::UpdateProfile { profile_pic, cover_banner, .. }
+                if profile_pic.is_some() || cover_banner.is_some() =>
+            {
+                RateLimitCategory::FileUploads
+            }
+            ClientMessage::RespondToServerInvite { .. }
+            | ClientMessage::AcceptServerInviteFromUser { .. }
+            | ClientMessage::JoinByInviteCode { .. }
+            | ClientMessage::JoinPublicServer { .. } => RateLimitCategory::ChannelJoins,
+            // Listed one by one so new variants have to pick a category
+            ClientMessage::UpdateProfile { .. }
+            | ClientMessage::Logout
+            | ClientMessage::GetSessions
+            | ClientMessage::RevokeSession { .. }
+            | ClientMessage::RevokeAllOtherSessions
+            | ClientMessage::BeginTotpEnrollment
+            | ClientMessage::UpdatePassword(..)
+            | ClientMessage::UpdateColor(..)
+            | ClientMessage::SetStatus { .. }
+            | ClientMessage::SetPresenceVisibility(..)
+            | ClientMessage::CancelAccountDeletion
+            | ClientMessage::ExportMyData
+            | ClientMessage::GetForums
+            | ClientMessage::GetForumIndex
+            | ClientMessage::GetForumThreads { .. }
+            | ClientMessage::GetThreadPosts { .. }
+            | ClientMessage::CreateForum { .. }
+            | ClientMessage::CreateForumCategory { .. }
+            | ClientMessage::DeleteForumCategory { .. }
+            | ClientMessage::ReorderForumCategories { .. }
+            | ClientMessage::SetForumCategory { .. }
+            | ClientMessage::CreateForumTag { .. }
+            | ClientMessage::DeleteForumTag { .. }
+            | ClientMessage::SetThreadTags { .. }
+            | ClientMessage::DeleteForum { .. }
+            | ClientMessage::GetPostRevisions { .. }
+            | ClientMessage::SubscribeThread { .. }
+            | ClientMessage::UnsubscribeThread { .. }
+            | ClientMessage::MuteThread { .. }
+            | ClientMessage::GetWatchedThreads
+            | ClientMessage::VotePost { .. }
+            | ClientMessage::MarkAcceptedAnswer { .. }
+            | ClientMessage::ClearAcceptedAnswer { .. }
+            | ClientMessage::DeclineServerInviteFromUser { .. }
+            | ClientMessage::RevokeInviteLink { .. }
+            | ClientMessage::GetInviteLinks { .. }
+            | ClientMessage::DeletePost(..)
+            | ClientMessage::DeleteThread(..)
+            | ClientMessage::PinThread { .. }
+            | ClientMessage::LockThread { .. }
+            | ClientMessage::MoveThread { .. }
+            | ClientMessage::KickFromServer { .. }
+            | ClientMessage::BanFromServer { .. }
+            | ClientMessage::MuteInChannel { .. }
+            | ClientMessage::Timeout { .. }
+            | ClientMessage::LiftSanction { .. }
+            | ClientMessage::GetServerSanctions { .. }
+            | ClientMessage::GetUserList
+            | ClientMessage::GetProfile { .. }
+            | ClientMessage::GetServers
+            | ClientMessage::DiscoverServers { .. }
+            | ClientMessage::SetServerTags { .. }
+            | ClientMessage::GetServerIcons { .. }
+            | ClientMessage::LeaveServer { .. }
+            | ClientMessage::TransferServerOwnership { .. }
+            | ClientMessage::DeleteServer { .. }
+            | ClientMessage::GetServerRoles { .. }
+            | ClientMessage::CreateServerRole { .. }
+            | ClientMessage::UpdateServerRole { .. }
+            | ClientMessage::DeleteServerRole { .. }
+            | ClientMessage::AssignServerRole { .. }
+            | ClientMessage::UnassignServerRole { .. }
+            | ClientMessage::RespondFriendRequest { .. }
+            | ClientMessage::RemoveFriend { .. }
+            | ClientMessage::GetFriends
+            | ClientMessage::BlockUser { .. }
+            | ClientMessage::UnblockUser { .. }
+            | ClientMessage::GetBlockedUsers
+            | ClientMessage::GetChannelMessagesPaginated { .. }
+            | ClientMessage::GetDirectMessagesPaginated { .. }
+            | ClientMessage::GetChannelMessages { .. }
+            | ClientMessage::GetChannelUserList { .. }
+            | ClientMessage::GetDMUserList
+            | ClientMessage::GetDirectMessages { .. }
+            | ClientMessage::GetNotifications { .. }
+            | ClientMessage::MarkNotificationRead { .. }
+            | ClientMessage::InvalidateImageCache { .. }
+            | ClientMessage::GetCacheStats
+            | ClientMessage::GetUserAvatars { .. } => RateLimitCategory::Requests,
+        }
+    }
+}
+
+/// Time source for the rate limiter, injectable so tests don't depend on wall time
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves when told to. Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    base: Instant,
+    offset: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self { base: Instant::now(), offset: Arc::new(Mutex::new(Duration::ZERO)) }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.offset.lock().unwrap() += by;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.base + *self.offset.lock().unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+    capacity: f64,
+    per_second: f64, // Refill rate
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Keyed token-bucket rate limiter.
+///
+/// Each (key, category) pair gets a bucket holding up to the configured limit,
+/// refilled evenly over the limit's window. A limit of 0 disables that category.
+#[derive(Debug)]
+pub struct RateLimiter<C: Clock = SystemClock> {
+    limits: HashMap<RateLimitCategory, Limit>,
+    buckets: HashMap<(RateLimitKey, RateLimitCategory), Bucket>,
+    clock: C,
+}
+
+impl RateLimiter<SystemClock> {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self::with_clock(config, SystemClock)
+    }
+}
+
+impl<C: Clock> RateLimiter<C> {
+    pub fn with_clock(config: &RateLimitConfig, clock: C) -> Self {
+        let limits = [
+            (RateLimitCategory::Messages, config.messages_per_minute, 60),
+            (RateLimitCategory::Requests, config.requests_per_second, 1),
+            (RateLimitCategory::FileUploads, config.file_uploads_per_hour, 3600),
+            (RateLimitCategory::Registration, config.registration_attempts_per_hour, 3600),
+            (RateLimitCategory::Login, config.login_attempts_per_minute, 60),
+            (RateLimitCategory::ChannelJoins, config.channel_joins_per_minute, 60),
+        ]
+        .into_iter()
+        .filter(|&(_, count, _)| count > 0)
+        .map(|(category, count, window_secs)| {
+            let capacity = count as f64;
+            (category, Limit { capacity, per_second: capacity / window_secs as f64 })
+        })
+        .collect();
+
+        Self { limits, buckets: HashMap::new(), clock }
+    }
+
+    /// Take one token for `key` in `category`.
+    /// Returns `Err(retry_after)` when the limit is exhausted; nothing is consumed in that case.
+    pub fn check(&mut self, key: RateLimitKey, category: RateLimitCategory) -> Result<(), Duration> {
+        let Some(limit) = self.limits.get(&category).copied() else {
+            return Ok(());
+        };
+        let now = self.clock.now();
+        let bucket = self
+            .buckets
+            .entry((key, category))
+            .or_insert(Bucket { tokens: limit.capacity, updated: now });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(limit.capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / limit.per_second))
+        }
+    }
+
+    /// Check a message against the category it maps to
+    pub fn check_message(&mut self, key: RateLimitKey, message: &ClientMessage) -> Result<(), Duration> {
+        self.check(key, message.rate_limit_category())
+    }
+
+    /// Forget every bucket belonging to `key`, e.g. after a successful login
+    pub fn reset(&mut self, key: RateLimitKey) {
+        self.buckets.retain(|(k, _), _| *k != key);
+    }
+
+    /// Drop buckets that have refilled completely; call periodically to bound memory
+    pub fn prune(&mut self) {
+        let now = self.clock.now();
+        let limits = &self.limits;
+        self.buckets.retain(|(_, category), bucket| match limits.get(category) {
+            Some(limit) => {
+                let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+                bucket.tokens + elapsed * limit.per_second < limit.capacity
+            }
+            None => false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> (RateLimiter<ManualClock>, ManualClock) {
+        let clock = ManualClock::new();
+        let config = crate::ServerConfig::default().rate_limits;
+        (RateLimiter::with_clock(&config, clock.clone()), clock)
+    }
+
+    #[test]
+    fn allows_burst_up_to_limit_then_reports_retry_after() {
+        let (mut limiter, _clock) = limiter();
+        let key = RateLimitKey::User(Uuid::new_v4());
+        for _ in 0..5 {
+            assert!(limiter.check(key, RateLimitCategory::Login).is_ok());
+        }
+        // 5 per minute refills one token every 12 seconds
+        let retry = limiter.check(key, RateLimitCategory::Login).unwrap_err();
+        assert_eq!(retry.as_secs(), 12);
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let (mut limiter, clock) = limiter();
+        let key = RateLimitKey::Ip("10.0.0.1".parse().unwrap());
+        for _ in 0..10 {
+            limiter.check(key, RateLimitCategory::Requests).unwrap();
+        }
+        assert!(limiter.check(key, RateLimitCategory::Requests).is_err());
+
+        clock.advance(Duration::from_millis(100));
+        assert!(limiter.check(key, RateLimitCategory::Requests).is_ok());
+        assert!(limiter.check(key, RateLimitCategory::Requests).is_err());
+
+        clock.advance(Duration::from_secs(60));
+        for _ in 0..10 {
+            assert!(limiter.check(key, RateLimitCategory::Requests).is_ok());
+        }
+    }
+
+    #[test]
+    fn keys_and_categories_are_independent() {
+        let (mut limiter, _clock) = limiter();
+        let alice = RateLimitKey::User(Uuid::new_v4());
+        let bob = RateLimitKey::User(Uuid::new_v4());
+        for _ in 0..5 {
+            limiter.check(alice, RateLimitCategory::Registration).unwrap();
+        }
+        assert!(limiter.check(alice, RateLimitCategory::Registration).is_err());
+        assert!(limiter.check(bob, RateLimitCategory::Registration).is_ok());
+        assert!(limiter.check(alice, RateLimitCategory::Messages).is_ok());
+    }
+
+    #[test]
+    fn zero_limit_disables_category() {
+        let clock = ManualClock::new();
+        let mut config = crate::ServerConfig::default().rate_limits;
+        config.login_attempts_per_minute = 0;
+        let mut limiter = RateLimiter::with_clock(&config, clock);
+        let key = RateLimitKey::User(Uuid::new_v4());
+        for _ in 0..100 {
+            assert!(limiter.check(key, RateLimitCategory::Login).is_ok());
+        }
+    }
+
+    #[test]
+    fn messages_map_to_categories() {
+        let login = ClientMessage::Login { username: "a".into(), password: "b".into(), client_name: None };
+        assert_eq!(login.rate_limit_category(), RateLimitCategory::Login);
+        let challenge_id = Uuid::new_v4();
+        for message in [
+            ClientMessage::SubmitTotp { challenge_id, code: "123456".into() },
+            ClientMessage::SubmitRecoveryCode { challenge_id, code: "abcd-efgh".into() },
+            ClientMessage::DisableTotp { password: "pw".into(), code: "123456".into() },
+            ClientMessage::ChangeUsername { new_username: "bob".into(), password: "pw".into() },
+            ClientMessage::DeleteAccount { password: "pw".into() },
+        ] {
+            assert_eq!(message.rate_limit_category(), RateLimitCategory::Login, "{:?}", message);
+        }
+        let dm = ClientMessage::SendDirectMessage { to: Uuid::new_v4(), content: "hi".into() };
+        assert_eq!(dm.rate_limit_category(), RateLimitCategory::Messages);
+        let edit = ClientMessage::EditPost { post_id: Uuid::new_v4(), content: "hi".into() };
+        assert_eq!(edit.rate_limit_category(), RateLimitCategory::Messages);
+        assert_eq!(ClientMessage::GetForums.rate_limit_category(), RateLimitCategory::Requests);
+    }
+
+    #[test]
+    fn prune_drops_refilled_buckets() {
+        let (mut limiter, clock) = limiter();
+        let key = RateLimitKey::User(Uuid::new_v4());
+        limiter.check(key, RateLimitCategory::Messages).unwrap();
+        limiter.prune();
+        assert_eq!(limiter.buckets.len(), 1);
+        clock.advance(Duration::from_secs(2));
+        limiter.prune();
+        assert!(limiter.buckets.is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::blocking::BlockList;
+use crate::{Post, Thread, ThreadWatch, WatchLevel};
+
+/// Level used for threads a user has no explicit setting for. Matches the
+/// old implicit rule: the thread author and the author of the replied-to post.
+pub const DEFAULT_WATCH_LEVEL: WatchLevel = WatchLevel::RepliesToMe;
+
+/// Server-side per-thread watch settings (thread -> user -> level)
+#[derive(Debug, Clone, Default)]
+pub struct ThreadWatches {
+    watches: HashMap<Uuid, HashMap<Uuid, WatchLevel>>,
+}
+
+impl ThreadWatches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self, user_id: Uuid, thread_id: Uuid, level: WatchLevel) {
+        self.watches.entry(thread_id).or_default().insert(user_id, level);
+    }
+
+    /// Drop the explicit setting, returning to `DEFAULT_WATCH_LEVEL`
+    pub fn unsubscribe(&mut self, user_id: Uuid, thread_id: Uuid) -> bool {
+        let Some(users) = self.watches.get_mut(&thread_id) else {
+            return false;
+        };
+        let removed = users.remove(&user_id).is_some();
+        if users.is_empty() {
+            self.watches.remove(&thread_id);
+        }
+        removed
+    }
+
+    pub fn mute(&mut self, user_id: Uuid, thread_id: Uuid) {
+        self.subscribe(user_id, thread_id, WatchLevel::Muted);
+    }
+
+    /// Subscribe to all posts after the user posts in a thread, unless they already
+    /// chose a level for it (a muted thread stays muted). Returns true if subscribed.
+    pub fn auto_subscribe(&mut self, user_id: Uuid, thread_id: Uuid) -> bool {
+        let users = self.watches.entry(thread_id).or_default();
+        if users.contains_key(&user_id) {
+            return false;
+        }
+        users.insert(user_id, WatchLevel::AllPosts);
+        true
+    }
+
+    /// The explicit setting, if any
+    pub fn level(&self, user_id: Uuid, thread_id: Uuid) -> Option<WatchLevel> {
+        self.watches.get(&thread_id)?.get(&user_id).copied()
+    }
+
+    pub fn effective_level(&self, user_id: Uuid, thread_id: Uuid) -> WatchLevel {
+        self.level(user_id, thread_id).unwrap_or(DEFAULT_WATCH_LEVEL)
+    }
+
+    /// Explicit settings for `ServerMessage::WatchedThreads`
+    pub fn watched_by(&self, user_id: Uuid) -> Vec<ThreadWatch> {
+        self.watches
+            .iter()
+            .filter_map(|(thread_id, users)| {
+                users.get(&user_id).map(|level| ThreadWatch { thread_id: *thread_id, level: *level })
+            })
+            .collect()
+    }
+
+    pub fn remove_thread(&mut self, thread_id: Uuid) {
+        self.watches.remove(&thread_id);
+    }
+
+    /// Forget a user's settings everywhere, e.g. on account deletion
+    pub fn remove_user(&mut self, user_id: Uuid) {
+        self.watches.retain(|_, users| {
+            users.remove(&user_id);
+            !users.is_empty()
+        });
+    }
+
+    /// Users to notify about `post` in `thread`. The post's author and anyone
+    /// who has blocked them are never included.
+    pub fn recipients(&self, thread: &Thread, post: &Post, blocks: &BlockList) -> HashSet<Uuid> {
+        let author = post.author.id;
+        let replied_to = post
+            .reply_to
+            .and_then(|id| thread.posts.iter().find(|p| p.id == id))
+            .map(|p| p.author.id);
+        let is_reply_to = |user_id: Uuid| match replied_to {
+            Some(replied_to) => replied_to == user_id,
+            None => thread.author.id == user_id,
+        };
+
+        let explicit = self.watches.get(&thread.id).into_iter().flat_map(|users| users.keys().copied());
+        explicit
+            .chain(std::iter::once(thread.author.id))
+            .chain(replied_to)
+            .filter(|id| *id != author && !blocks.is_blocked(*id, author))
+            .filter(|id| match self.effective_level(*id, thread.id) {
+                WatchLevel::AllPosts => true,
+                WatchLevel::RepliesToMe => is_reply_to(*id),
+                WatchLevel::Muted => false,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_user;
+
+    fn post(author: Uuid, reply_to: Option<Uuid>) -> Post {
+        Post {
+            id: Uuid::new_v4(),
+            author: test_user(author),
+            content: "hi".to_string(),
+            timestamp: 0,
+            reply_to,
+            edited_at: None,
+            score: 0,
+        }
+    }
+
+    /// Thread started by `author` with its opening post
+    fn thread(author: Uuid) -> Thread {
+        Thread {
+            id: Uuid::new_v4(),
+            title: "Question".to_string(),
+            author: test_user(author),
+            posts: vec![post(author, None)],
+            timestamp: 0,
+            edited_at: None,
+            pinned: false,
+            locked: false,
+            tags: vec![],
+            accepted_answer: None,
+        }
+    }
+
+    #[test]
+    fn all_posts_watchers_get_everything() {
+        let (starter, watcher, poster) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut thread = thread(starter);
+        let mut watches = ThreadWatches::new();
+        watches.subscribe(watcher, thread.id, WatchLevel::AllPosts);
+
+        let top_level = post(poster, None);
+        let recipients = watches.recipients(&thread, &top_level, &BlockList::new());
+        assert_eq!(recipients, HashSet::from([starter, watcher]));
+
+        thread.posts.push(top_level.clone());
+        let reply = post(starter, Some(top_level.id));
+        assert_eq!(watches.recipients(&thread, &reply, &BlockList::new()), HashSet::from([watcher, poster]));
+    }
+
+    #[test]
+    fn replies_to_me_covers_direct_replies_and_the_starters_top_level_posts() {
+        let (starter, replier, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut thread = thread(starter);
+        let watches = ThreadWatches::new(); // Everyone on DEFAULT_WATCH_LEVEL
+        let blocks = BlockList::new();
+
+        let top_level = post(replier, None);
+        assert_eq!(watches.recipients(&thread, &top_level, &blocks), HashSet::from([starter]));
+
+        thread.posts.push(top_level.clone());
+        // A reply to someone else's post doesn't reach the thread starter
+        let reply = post(other, Some(top_level.id));
+        assert_eq!(watches.recipients(&thread, &reply, &blocks), HashSet::from([replier]));
+    }
+
+    #[test]
+    fn muted_authors_and_blockers_are_skipped() {
+        let (starter, blocker, poster) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let thread = thread(starter);
+        let mut watches = ThreadWatches::new();
+        watches.mute(starter, thread.id);
+        watches.subscribe(blocker, thread.id, WatchLevel::AllPosts);
+        watches.subscribe(poster, thread.id, WatchLevel::AllPosts);
+        let mut blocks = BlockList::new();
+        blocks.block(blocker, poster);
+
+        assert!(watches.recipients(&thread, &post(poster, None), &blocks).is_empty());
+        assert_eq!(watches.effective_level(starter, thread.id), WatchLevel::Muted);
+    }
+
+    #[test]
+    fn auto_subscribe_respects_existing_choices() {
+        let (muted, chosen, fresh) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let thread_id = Uuid::new_v4();
+        let mut watches = ThreadWatches::new();
+        watches.mute(muted, thread_id);
+        watches.subscribe(chosen, thread_id, WatchLevel::RepliesToMe);
+
+        assert!(!watches.auto_subscribe(muted, thread_id));
+        assert!(!watches.auto_subscribe(chosen, thread_id));
+        assert!(watches.auto_subscribe(fresh, thread_id));
+        assert_eq!(watches.level(muted, thread_id), Some(WatchLevel::Muted));
+        assert_eq!(watches.level(chosen, thread_id), Some(WatchLevel::RepliesToMe));
+        assert_eq!(watches.level(fresh, thread_id), Some(WatchLevel::AllPosts));
+
+        assert!(watches.unsubscribe(muted, thread_id));
+        assert_eq!(watches.effective_level(muted, thread_id), DEFAULT_WATCH_LEVEL);
+    }
+
+    #[test]
+    fn subscribe_without_a_level_watches_all_posts() {
+        let thread_id = Uuid::new_v4();
+        let json = format!(r#"{{"SubscribeThread":{{"thread_id":"{}"}}}}"#, thread_id);
+        let message: crate::ClientMessage = serde_json::from_str(&json).unwrap();
+        assert!(matches!(message, crate::ClientMessage::SubscribeThread { level: WatchLevel::AllPosts, .. }));
+    }
+}
@@ -0,0 +1,38 @@
+//! Shared fixtures for unit tests
+
+use uuid::Uuid;
+
+use crate::{Server, User, UserColor, UserRole, UserStatus};
+
+pub fn test_user(id: Uuid) -> User {
+    User {
+        id,
+        username: "alice".to_string(),
+        color: UserColor::new("Cyan"),
+        role: UserRole::User,
+        profile_pic: None,
+        cover_banner: None,
+        status: UserStatus::Connected,
+        presence: None,
+    }
+}
+
+/// Private server owned by `owner`, with `owner` and `members` in its member list
+pub fn test_server(owner: Uuid, members: &[Uuid]) -> Server {
+    Server {
+        id: Uuid::new_v4(),
+        name: "Test".to_string(),
+        description: String::new(),
+        public: false,
+        invite_code: None,
+        icon: None,
+        banner: None,
+        owner,
+        mods: vec![],
+        userlist: std::iter::once(owner).chain(members.iter().copied()).collect(),
+        channels: vec![],
+        roles: vec![],
+        role_assignments: Default::default(),
+        tags: vec![],
+    }
+}
@@ -0,0 +1,278 @@
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha1::Sha1;
+
+/// RFC 4648 base32 alphabet used by authenticator apps
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Recovery code alphabet without look-alikes (0/O, 1/I/L)
+const RECOVERY_ALPHABET: &[u8] = b"23456789abcdefghjkmnpqrstuvwxyz";
+
+/// RFC 6238 time-based one-time passwords (HMAC-SHA1, as supported by every authenticator app)
+#[derive(Clone, PartialEq, Eq)]
+pub struct Totp {
+    secret: Vec<u8>,
+    digits: u32,
+    period: u64,   // Seconds per step
+    pub skew: u64, // Steps accepted either side of the current one to absorb clock drift
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotpError {
+    InvalidDigits,
+    InvalidPeriod,
+}
+
+impl std::fmt::Display for TotpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TotpError::InvalidDigits => write!(f, "Codes must be 6 to 8 digits long."),
+            TotpError::InvalidPeriod => write!(f, "The time step must be at least one second."),
+        }
+    }
+}
+
+impl std::error::Error for TotpError {}
+
+impl Totp {
+    /// 6 digits, 30 second steps, one step of drift allowed
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self { secret, digits: 6, period: 30, skew: 1 }
+    }
+
+    /// Custom code length and step. RFC 4226 requires at least 6 digits and
+    /// authenticator apps stop at 8; a zero period would divide by zero.
+    pub fn with_settings(secret: Vec<u8>, digits: u32, period: u64) -> Result<Self, TotpError> {
+        if !(6..=8).contains(&digits) {
+            return Err(TotpError::InvalidDigits);
+        }
+        if period == 0 {
+            return Err(TotpError::InvalidPeriod);
+        }
+        Ok(Self { digits, period, ..Self::new(secret) })
+    }
+
+    pub fn digits(&self) -> u32 {
+        self.digits
+    }
+
+    pub fn period(&self) -> u64 {
+        self.period
+    }
+
+    /// Fresh 160-bit secret, the size RFC 4226 recommends
+    pub fn generate() -> Self {
+        let mut secret = vec![0u8; 20];
+        rand::thread_rng().fill(&mut secret[..]);
+        Self::new(secret)
+    }
+
+    pub fn from_base32(secret: &str) -> Option<Self> {
+        base32_decode(secret).map(Self::new)
+    }
+
+    pub fn secret_base32(&self) -> String {
+        base32_encode(&self.secret)
+    }
+
+    /// Code for the step containing `unix_time` (seconds)
+    pub fn code_at(&self, unix_time: u64) -> String {
+        self.code_for_step(unix_time / self.period)
+    }
+
+    /// Verify a code, returning the matched step so the caller can refuse to
+    /// accept the same step twice (store it and pass it back as `last_used_step`).
+    pub fn verify(&self, code: &str, unix_time: u64, last_used_step: Option<u64>) -> Option<u64> {
+        let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+        if code.len() != self.digits as usize {
+            return None;
+        }
+        let current = unix_time / self.period;
+        (current.saturating_sub(self.skew)..=current + self.skew)
+            .filter(|step| last_used_step.is_none_or(|last| *step > last))
+            .find(|step| constant_time_eq(self.code_for_step(*step).as_bytes(), code.as_bytes()))
+    }
+
+    /// `otpauth://` URI to render as a QR code during enrollment
+    pub fn otpauth_uri(&self, issuer: &str, account: &str) -> String {
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            percent_encode(issuer),
+            percent_encode(account),
+            self.secret_base32(),
+            percent_encode(issuer),
+            self.digits,
+            self.period
+        )
+    }
+
+    fn code_for_step(&self, step: u64) -> String {
+        let mut mac = Hmac::<Sha1>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(&step.to_be_bytes());
+        let digest = mac.finalize().into_bytes();
+
+        // Dynamic truncation, RFC 4226 section 5.3
+        let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]]) & 0x7fff_ffff;
+        let code = binary as u64 % 10u64.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+}
+
+// Manual impl so the secret never ends up in logs
+impl std::fmt::Debug for Totp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Totp")
+            .field("secret", &"<redacted>")
+            .field("digits", &self.digits)
+            .field("period", &self.period)
+            .field("skew", &self.skew)
+            .finish()
+    }
+}
+
+/// One-time recovery codes in `xxxxx-xxxxx` form, shown once at enrollment
+pub fn generate_recovery_codes(count: usize) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..count)
+        .map(|_| {
+            let mut code: String = (0..10)
+                .map(|_| RECOVERY_ALPHABET[rng.gen_range(0..RECOVERY_ALPHABET.len())] as char)
+                .collect();
+            code.insert(5, '-');
+            code
+        })
+        .collect()
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// Lenient decoder: ignores case, spaces, dashes and padding as typed by users
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in input.chars().filter(|c| !matches!(c, ' ' | '-' | '=')) {
+        let value = BASE32_ALPHABET.iter().position(|&a| a as char == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    if out.is_empty() {
+        None
+    } else {
+        Some(out)
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rfc_totp() -> Totp {
+        // RFC 6238 appendix B, SHA1 seed
+        Totp::with_settings(b"12345678901234567890".to_vec(), 8, 30).unwrap()
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let secret = b"12345678901234567890".to_vec();
+        assert_eq!(Totp::with_settings(secret.clone(), 6, 0), Err(TotpError::InvalidPeriod));
+        assert_eq!(Totp::with_settings(secret.clone(), 5, 30), Err(TotpError::InvalidDigits));
+        assert_eq!(Totp::with_settings(secret.clone(), 20, 30), Err(TotpError::InvalidDigits));
+        assert!(Totp::with_settings(secret, 8, 60).is_ok());
+    }
+
+    #[test]
+    fn matches_rfc6238_vectors() {
+        let totp = rfc_totp();
+        let vectors = [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1111111111, "14050471"),
+            (1234567890, "89005924"),
+            (2000000000, "69279037"),
+            (20000000000, "65353130"),
+        ];
+        for (time, code) in vectors {
+            assert_eq!(totp.code_at(time), code, "at {}", time);
+        }
+    }
+
+    #[test]
+    fn accepts_one_step_of_drift() {
+        let totp = Totp::new(b"12345678901234567890".to_vec());
+        let now = 1_700_000_000;
+        let previous = totp.code_at(now - 30);
+        let next = totp.code_at(now + 30);
+        assert_eq!(totp.verify(&previous, now, None), Some(now / 30 - 1));
+        assert_eq!(totp.verify(&next, now, None), Some(now / 30 + 1));
+    }
+
+    #[test]
+    fn rejects_codes_outside_window() {
+        let totp = Totp::new(b"12345678901234567890".to_vec());
+        let now = 1_700_000_000;
+        assert_eq!(totp.verify(&totp.code_at(now - 60), now, None), None);
+        assert_eq!(totp.verify(&totp.code_at(now + 60), now, None), None);
+        assert_eq!(totp.verify("12345", now, None), None);
+    }
+
+    #[test]
+    fn rejects_replayed_step() {
+        let totp = Totp::new(b"12345678901234567890".to_vec());
+        let now = 1_700_000_000;
+        let code = totp.code_at(now);
+        let step = totp.verify(&code, now, None).unwrap();
+        assert_eq!(totp.verify(&code, now + 5, Some(step)), None);
+    }
+
+    #[test]
+    fn base32_round_trips() {
+        let totp = Totp::generate();
+        let decoded = Totp::from_base32(&totp.secret_base32().to_lowercase()).unwrap();
+        assert!(decoded == totp);
+        assert_eq!(base32_encode(b"12345678901234567890"), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+    }
+
+    #[test]
+    fn otpauth_uri_is_escaped() {
+        let totp = Totp::from_base32("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").unwrap();
+        assert_eq!(
+            totp.otpauth_uri("Nexus", "alice smith"),
+            "otpauth://totp/Nexus:alice%20smith?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Nexus&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use serde::{Deserialize, Serialize};
+
+use crate::moderation::fold_char;
+
+pub const USERNAME_MIN_LEN: usize = 3;
+pub const USERNAME_MAX_LEN: usize = 20;
+pub const BIO_MAX_LEN: usize = 500;
+pub const LOCATION_MAX_LEN: usize = 100;
+pub const URL_MAX_LEN: usize = 200;
+pub const SERVER_TAG_MAX_LEN: usize = 24;
+pub const SERVER_TAGS_MAX: usize = 5;
+
+/// Names nobody can register, compared by skeleton so "Syst3m" and "SYSTEM" are caught too.
+/// "system" is the author of the forums seeded by `create_initial_forums`.
+pub const RESERVED_USERNAMES: &[&str] = &[
+    "system",
+    "admin",
+    "administrator",
+    "moderator",
+    "mod",
+    "root",
+    "nexus",
+    "server",
+    "support",
+    "staff",
+    "everyone",
+    "here",
+    "null",
+    "undefined",
+];
+
+/// URL schemes accepted in profile links
+pub const ALLOWED_URL_SCHEMES: &[&str] = &["http", "https"];
+
+/// Which input a `FieldError` belongs to
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Username,
+    Bio,
+    Url1,
+    Url2,
+    Url3,
+    Location,
+    ServerTags,
+}
+
+/// Why a single field was rejected, structured so the client can show it next to the input
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum FieldError {
+    Empty,
+    TooShort { min: usize },
+    TooLong { max: usize },
+    InvalidCharacters,
+    Confusable, // Uses look-alike characters from another script
+    Reserved,
+    Taken,
+    InvalidUrl,
+    UnsupportedScheme,
+    TooMany { max: usize },
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldError::Empty => write!(f, "Cannot be empty"),
+            FieldError::TooShort { min } => write!(f, "Must be at least {} characters", min),
+            FieldError::TooLong { max } => write!(f, "Must be at most {} characters", max),
+            FieldError::InvalidCharacters => write!(f, "Contains characters that are not allowed"),
+            FieldError::Confusable => write!(f, "Contains look-alike characters; use plain letters and digits"),
+            FieldError::Reserved => write!(f, "This name is reserved"),
+            FieldError::Taken => write!(f, "Already taken"),
+            FieldError::InvalidUrl => write!(f, "Not a valid URL"),
+            FieldError::UnsupportedScheme => write!(f, "Only http and https links are allowed"),
+            FieldError::TooMany { max } => write!(f, "At most {} allowed", max),
+        }
+    }
+}
+
+/// A field-level error as sent to the client
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FieldIssue {
+    pub field: Field,
+    pub error: FieldError,
+}
+
+/// Usernames are 3-20 ASCII letters, digits, `_` or `-`, starting with a letter or digit,
+/// and must not resemble a reserved name
+pub fn validate_username(username: &str) -> Result<(), FieldError> {
+    let length = username.chars().count();
+    if length == 0 {
+        return Err(FieldError::Empty);
+    }
+    if length < USERNAME_MIN_LEN {
+        return Err(FieldError::TooShort { min: USERNAME_MIN_LEN });
+    }
+    if length > USERNAME_MAX_LEN {
+        return Err(FieldError::TooLong { max: USERNAME_MAX_LEN });
+    }
+    if username
+        .chars()
+        .any(|c| !c.is_ascii() && fold_char(c).is_none_or(|f| f.is_ascii_alphanumeric()))
+    {
+        return Err(FieldError::Confusable);
+    }
+    if !username.starts_with(|c: char| c.is_ascii_alphanumeric())
+        || !username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(FieldError::InvalidCharacters);
+    }
+    let key = username_key(username);
+    if RESERVED_USERNAMES.iter().any(|r| username_key(r) == key) {
+        return Err(FieldError::Reserved);
+    }
+    Ok(())
+}
+
+/// Canonical skeleton used for uniqueness checks, so look-alikes such as
+/// "Alice", "a1ice" and "al_ice" can't coexist
+pub fn username_key(username: &str) -> String {
+    username
+        .trim()
+        .chars()
+        .filter_map(fold_char)
+        .filter(|c| *c != '_' && *c != '-')
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | 'i' => 'l',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            '8' => 'b',
+            other => other,
+        })
+        .collect::<String>()
+        .replace("rn", "m")
+        .replace("vv", "w")
+}
+
+/// Validate a new username and check it against the names already in use
+pub fn validate_new_username<'a>(
+    username: &str,
+    existing: impl IntoIterator<Item = &'a str>,
+) -> Result<(), FieldError> {
+    validate_username(username)?;
+    let key = username_key(username);
+    if existing.into_iter().any(|e| username_key(e) == key) {
+        return Err(FieldError::Taken);
+    }
+    Ok(())
+}
+
+/// Free text shown on a profile: length limit, no control characters
+/// (newlines are allowed when `multiline` is set)
+pub fn validate_text(text: &str, max: usize, multiline: bool) -> Result<(), FieldError> {
+    if text.chars().count() > max {
+        return Err(FieldError::TooLong { max });
+    }
+    if text.chars().any(|c| c.is_control() && !(multiline && c == '\n')) {
+        return Err(FieldError::InvalidCharacters);
+    }
+    Ok(())
+}
+
+/// Profile links must be absolute http(s) URLs with a host, an optional valid port,
+/// no embedded credentials and no whitespace
+pub fn validate_url(url: &str) -> Result<(), FieldError> {
+    if url.chars().count() > URL_MAX_LEN {
+        return Err(FieldError::TooLong { max: URL_MAX_LEN });
+    }
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(FieldError::InvalidUrl);
+    }
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return Err(FieldError::InvalidUrl);
+    };
+    if !ALLOWED_URL_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()) {
+        return Err(FieldError::UnsupportedScheme);
+    }
+    let Some(rest) = rest.strip_prefix("//") else {
+        return Err(FieldError::InvalidUrl);
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    // `user:pw@host` links are a phishing staple ("https://bank.com@evil.example")
+    if authority.contains('@') {
+        return Err(FieldError::InvalidUrl);
+    }
+    let (host, port) = match authority.split_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (authority, None),
+    };
+    // ASCII only: internationalized domains must arrive as punycode, so a
+    // Cyrillic "а" can't pass for a Latin "a" ("https://аpple.com")
+    if host.is_empty() || !host.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-') {
+        return Err(FieldError::InvalidUrl);
+    }
+    if port.is_some_and(|p| !p.parse::<u16>().is_ok_and(|p| p > 0)) {
+        return Err(FieldError::InvalidUrl);
+    }
+    Ok(())
+}
+
+/// Validate the fields of `ClientMessage::UpdateProfile`, collecting every error.
+/// `None` and empty strings clear a field and are always accepted.
+pub fn validate_profile_update(
+    bio: Option<&str>,
+    url1: Option<&str>,
+    url2: Option<&str>,
+    url3: Option<&str>,
+    location: Option<&str>,
+) -> Result<(), Vec<FieldIssue>> {
+    let mut issues = Vec::new();
+    let mut check = |field: Field, value: Option<&str>, rule: &dyn Fn(&str) -> Result<(), FieldError>| {
+        if let Some(error) = value.filter(|v| !v.is_empty()).and_then(|v| rule(v).err()) {
+            issues.push(FieldIssue { field, error });
+        }
+    };
+    check(Field::Bio, bio, &|v| validate_text(v, BIO_MAX_LEN, true));
+    check(Field::Url1, url1, &validate_url);
+    check(Field::Url2, url2, &validate_url);
+    check(Field::Url3, url3, &validate_url);
+    check(Field::Location, location, &|v| validate_text(v, LOCATION_MAX_LEN, false));
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
+
+/// Lowercase, trim and de-duplicate server tags, rejecting any that aren't
+/// 1-24 characters of letters, digits or `-`
+pub fn normalize_server_tags(tags: &[String]) -> Result<Vec<String>, FieldError> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.chars().count() > SERVER_TAG_MAX_LEN {
+            return Err(FieldError::TooLong { max: SERVER_TAG_MAX_LEN });
+        }
+        if !tag.chars().all(|c| c.is_alphanumeric() || c == '-') {
+            return Err(FieldError::InvalidCharacters);
+        }
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > SERVER_TAGS_MAX {
+        return Err(FieldError::TooMany { max: SERVER_TAGS_MAX });
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn username_format() {
+        assert_eq!(validate_username("alice_01"), Ok(()));
+        assert_eq!(validate_username(""), Err(FieldError::Empty));
+        assert_eq!(validate_username("al"), Err(FieldError::TooShort { min: USERNAME_MIN_LEN }));
+        assert_eq!(validate_username(&"a".repeat(21)), Err(FieldError::TooLong { max: USERNAME_MAX_LEN }));
+        assert_eq!(validate_username("_alice"), Err(FieldError::InvalidCharacters));
+        assert_eq!(validate_username("ali ce"), Err(FieldError::InvalidCharacters));
+    }
+
+    #[test]
+    fn reserved_names_and_look_alikes() {
+        for name in ["system", "SYSTEM", "Syst3m", "5ystem", "sys_tem", "Admin", "r00t"] {
+            assert_eq!(validate_username(name), Err(FieldError::Reserved), "{}", name);
+        }
+        assert_eq!(validate_username("systematic"), Ok(()));
+    }
+
+    #[test]
+    fn confusable_characters_are_rejected() {
+        // Cyrillic а, fullwidth ａ, an accented é and a zero-width space
+        for name in ["\u{430}lice", "\u{FF41}lice", "ren\u{E9}e", "ali\u{200B}ce"] {
+            assert_eq!(validate_username(name), Err(FieldError::Confusable), "{}", name);
+        }
+        assert_eq!(validate_username("ali\u{2603}ce"), Err(FieldError::InvalidCharacters));
+    }
+
+    #[test]
+    fn username_keys_collide_for_look_alikes() {
+        let key = username_key("alice");
+        for name in ["Alice", "a1ice", "al_ice", "AL-ICE", " alice "] {
+            assert_eq!(username_key(name), key, "{}", name);
+        }
+        assert_eq!(username_key("modern"), username_key("modem"));
+        assert_ne!(username_key("alice"), username_key("alicia"));
+    }
+
+    #[test]
+    fn new_usernames_must_be_unique_by_key() {
+        let existing = ["Alice", "bob"];
+        assert_eq!(validate_new_username("a1ice", existing), Err(FieldError::Taken));
+        assert_eq!(validate_new_username("carol", existing), Ok(()));
+        assert_eq!(validate_new_username("admin", existing), Err(FieldError::Reserved));
+    }
+
+    #[test]
+    fn urls() {
+        for url in ["https://example.com", "http://example.com:8080/path?q=1#x", "HTTPS://sub.example.org/"] {
+            assert_eq!(validate_url(url), Ok(()), "{}", url);
+        }
+        for url in ["javascript:alert(1)", "ftp://example.com", "data:text/html,hi"] {
+            assert_eq!(validate_url(url), Err(FieldError::UnsupportedScheme), "{}", url);
+        }
+        for url in [
+            "example.com",
+            "https:example.com",
+            "https://",
+            "https://exa mple.com",
+            "https://user:pw@example.com",
+            "https://bank.com@evil.example",
+            "https://example.com:99999",
+            "https://example.com:0",
+            "https://example.com:",
+            "https://exa_mple.com",
+            "https://\u{430}pple.com",
+            "https://xn--pple-43d.com\u{200b}",
+        ] {
+            assert_eq!(validate_url(url), Err(FieldError::InvalidUrl), "{}", url);
+        }
+        assert_eq!(validate_url("https://xn--pple-43d.com/"), Ok(()));
+        let long = format!("https://example.com/{}", "a".repeat(URL_MAX_LEN));
+        assert_eq!(validate_url(&long), Err(FieldError::TooLong { max: URL_MAX_LEN }));
+    }
+
+    #[test]
+    fn profile_update_collects_every_issue() {
+        assert_eq!(validate_profile_update(Some(""), None, Some(""), None, None), Ok(()));
+        let issues = validate_profile_update(Some("ok"), Some("ftp://x"), None, Some("https://a@b"), Some("a\nb"))
+            .unwrap_err();
+        assert_eq!(
+            issues,
+            vec![
+                FieldIssue { field: Field::Url1, error: FieldError::UnsupportedScheme },
+                FieldIssue { field: Field::Url3, error: FieldError::InvalidUrl },
+                FieldIssue { field: Field::Location, error: FieldError::InvalidCharacters },
+            ]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::forum::ForumError;
+use crate::{Post, PostLightweight, Vote};
+
+/// Server-side vote records (post -> voter -> vote). Only the resulting
+/// `Post.score` and each user's own vote are ever sent to clients.
+#[derive(Debug, Clone, Default)]
+pub struct PostVotes {
+    votes: HashMap<Uuid, HashMap<Uuid, Vote>>,
+}
+
+impl PostVotes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set or withdraw `voter`'s vote on `post` and update its score, returning the new score
+    pub fn vote(&mut self, post: &mut Post, voter: Uuid, vote: Option<Vote>) -> Result<i64, ForumError> {
+        if voter == post.author.id {
+            return Err(ForumError::OwnPost);
+        }
+        let votes = self.votes.entry(post.id).or_default();
+        match vote {
+            Some(vote) => votes.insert(voter, vote),
+            None => votes.remove(&voter),
+        };
+        if votes.is_empty() {
+            self.votes.remove(&post.id);
+        }
+        post.score = self.score_of(post.id);
+        Ok(post.score)
+    }
+
+    pub fn vote_of(&self, post_id: Uuid, user_id: Uuid) -> Option<Vote> {
+        self.votes.get(&post_id)?.get(&user_id).copied()
+    }
+
+    /// Lightweight copy of `post` carrying `viewer`'s own vote
+    pub fn lightweight_for(&self, post: &Post, viewer: Uuid) -> PostLightweight {
+        PostLightweight { my_vote: self.vote_of(post.id, viewer), ..post.into() }
+    }
+
+    pub fn remove_post(&mut self, post_id: Uuid) {
+        self.votes.remove(&post_id);
+    }
+
+    /// Forget a user's votes, e.g. on account deletion. Scores of the affected
+    /// posts are stale until recalculated with `score_of`.
+    pub fn remove_user(&mut self, user_id: Uuid) -> Vec<Uuid> {
+        let mut affected = Vec::new();
+        self.votes.retain(|post_id, voters| {
+            if voters.remove(&user_id).is_some() {
+                affected.push(*post_id);
+            }
+            !voters.is_empty()
+        });
+        affected
+    }
+
+    /// Score recomputed from the stored votes
+    pub fn score_of(&self, post_id: Uuid) -> i64 {
+        self.votes.get(&post_id).map_or(0, |voters| {
+            voters
+                .values()
+                .map(|v| match v {
+                    Vote::Up => 1,
+                    Vote::Down => -1,
+                })
+                .sum()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_user;
+
+    fn post(author: Uuid) -> Post {
+        Post {
+            id: Uuid::new_v4(),
+            author: test_user(author),
+            content: "answer".to_string(),
+            timestamp: 0,
+            reply_to: None,
+            edited_at: None,
+            score: 0,
+        }
+    }
+
+    #[test]
+    fn votes_update_score_and_can_be_changed_or_withdrawn() {
+        let mut votes = PostVotes::new();
+        let mut post = post(Uuid::new_v4());
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        assert_eq!(votes.vote(&mut post, a, Some(Vote::Up)), Ok(1));
+        assert_eq!(votes.vote(&mut post, b, Some(Vote::Up)), Ok(2));
+        assert_eq!(votes.vote(&mut post, b, Some(Vote::Down)), Ok(0));
+        assert_eq!(votes.vote(&mut post, a, None), Ok(-1));
+        assert_eq!(post.score, -1);
+        assert_eq!(votes.lightweight_for(&post, b).my_vote, Some(Vote::Down));
+        assert_eq!(votes.lightweight_for(&post, a).my_vote, None);
+    }
+
+    #[test]
+    fn authors_cannot_vote_on_their_own_posts() {
+        let author = Uuid::new_v4();
+        let mut post = post(author);
+        assert_eq!(PostVotes::new().vote(&mut post, author, Some(Vote::Up)), Err(ForumError::OwnPost));
+        assert_eq!(post.score, 0);
+    }
+
+    #[test]
+    fn posts_never_serialize_voters() {
+        let mut votes = PostVotes::new();
+        let mut post = post(Uuid::new_v4());
+        let voter = Uuid::new_v4();
+        votes.vote(&mut post, voter, Some(Vote::Up)).unwrap();
+        let json = serde_json::to_string(&post).unwrap();
+        assert!(!json.contains(&voter.to_string()), "{}", json);
+    }
+}